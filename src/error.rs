@@ -0,0 +1,102 @@
+use std::{error, fmt, io, result};
+
+/// A specialized `Result` type for inference, optimization and generation
+pub type Result<T> = result::Result<T, Error>;
+
+/// The error type for inference, optimization and generation
+#[derive(Debug)]
+pub enum Error {
+    /// The input is not valid JSON
+    Parse {
+        message: String,
+        /// 1-based line number where the error occurs
+        line: usize,
+        /// 1-based column number where the error occurs
+        column: usize,
+    },
+    /// No sample is given to infer from, e.g. when an NDJSON input is empty
+    NoSamples,
+    /// A `$ref` in a JSON Schema that cannot be resolved
+    InvalidReference(String),
+    /// A schema IR that cannot be loaded, e.g. with a reference to an undefined type
+    InvalidIR(String),
+    /// A naming override with an invalid JSON path, or one that leads to no type to be named
+    InvalidNaming(String),
+    /// A type is absent from the arena or not of the expected kind, which indicates a bug
+    MalformedSchema(String),
+    /// A target generator fails to write its output
+    Generation(fmt::Error),
+    /// The input fails to be read
+    Io(io::Error),
+}
+
+impl Error {
+    /// Get the 1-based line and column numbers where the error occurs in the input, if any
+    pub fn position(&self) -> Option<(usize, usize)> {
+        match *self {
+            Error::Parse { line, column, .. } => Some((line, column)),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Parse {
+                ref message,
+                line,
+                column,
+            } => write!(
+                f,
+                "invalid JSON at line {} column {}: {}",
+                line, column, message
+            ),
+            Error::NoSamples => write!(f, "no samples in input"),
+            Error::InvalidReference(ref reference) => {
+                write!(f, "unresolvable reference in JSON Schema: {}", reference)
+            }
+            Error::InvalidIR(ref reason) => write!(f, "invalid schema IR: {}", reason),
+            Error::InvalidNaming(ref reason) => write!(f, "invalid naming override: {}", reason),
+            Error::MalformedSchema(ref reason) => write!(f, "malformed schema: {}", reason),
+            Error::Generation(_) => write!(f, "failed to generate output"),
+            Error::Io(ref e) => write!(f, "failed to read input: {}", e),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Error::Generation(ref e) => Some(e),
+            Error::Io(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        if e.is_io() {
+            return Error::Io(e.into());
+        }
+        let (line, column) = (e.line(), e.column());
+        // The position is kept separately instead of as a suffix in the message.
+        let message = e.to_string();
+        let message = message
+            .strip_suffix(&format!(" at line {} column {}", line, column))
+            .unwrap_or(&message)
+            .to_owned();
+        Error::Parse {
+            message,
+            line,
+            column,
+        }
+    }
+}
+
+impl From<fmt::Error> for Error {
+    fn from(e: fmt::Error) -> Self {
+        Error::Generation(e)
+    }
+}
@@ -0,0 +1,197 @@
+//! Detect formats of strings, e.g. dates, URLs and decimal numbers
+use std::net::IpAddr;
+
+use crate::schema::Primitive;
+
+/// Detect the format of a string, other than those always detected (i.e. date-times and UUIDs)
+///
+/// The checks are deliberately strict, so that a field is not given a format by a coincidence.
+pub fn detect(value: &str) -> Option<Primitive> {
+    if is_plain_date(value) {
+        Some(Primitive::PlainDate)
+    } else if is_time(value) {
+        Some(Primitive::Time)
+    } else if is_duration(value) {
+        Some(Primitive::Duration)
+    } else if value.parse::<IpAddr>().is_ok() {
+        Some(Primitive::IPAddress)
+    } else if is_decimal(value) {
+        Some(Primitive::Decimal)
+    } else if is_email(value) {
+        Some(Primitive::Email)
+    } else if is_url(value) {
+        Some(Primitive::URL)
+    } else if is_base64(value) {
+        Some(Primitive::Bytes)
+    } else {
+        None
+    }
+}
+
+/// Check if a string is a number of exactly two digits no greater than `max`
+fn is_two_digits_upto(s: &str, max: u8) -> bool {
+    s.len() == 2 && s.bytes().all(|b| b.is_ascii_digit()) && s.parse::<u8>().unwrap() <= max
+}
+
+/// Check if a string is like `2024-01-31`
+fn is_plain_date(value: &str) -> bool {
+    let mut parts = value.split('-');
+    match (parts.next(), parts.next(), parts.next(), parts.next()) {
+        (Some(year), Some(month), Some(day), None) => {
+            year.len() == 4
+                && year.bytes().all(|b| b.is_ascii_digit())
+                && is_two_digits_upto(month, 12)
+                && month != "00"
+                && is_two_digits_upto(day, 31)
+                && day != "00"
+        }
+        _ => false,
+    }
+}
+
+/// Check if a string is like `08:00`, `08:00:00.123` or `08:00:00+08:00`
+fn is_time(value: &str) -> bool {
+    let (time, offset) = match value.strip_suffix('Z') {
+        Some(time) => (time, None),
+        None => match value.rfind(['+', '-']) {
+            Some(i) => (&value[..i], Some(&value[i + 1..])),
+            None => (value, None),
+        },
+    };
+    if let Some(offset) = offset {
+        match offset.split_once(':') {
+            Some((hours, minutes))
+                if is_two_digits_upto(hours, 23) && is_two_digits_upto(minutes, 59) => {}
+            _ => return false,
+        }
+    }
+    let (time, fraction) = match time.split_once('.') {
+        Some((time, fraction)) => (time, Some(fraction)),
+        None => (time, None),
+    };
+    if fraction.is_some_and(|fraction| {
+        fraction.is_empty() || !fraction.bytes().all(|b| b.is_ascii_digit())
+    }) {
+        return false;
+    }
+    let parts: Vec<&str> = time.split(':').collect();
+    match parts[..] {
+        [hours, minutes] => {
+            fraction.is_none() && is_two_digits_upto(hours, 23) && is_two_digits_upto(minutes, 59)
+        }
+        [hours, minutes, seconds] => {
+            // 60 is for leap seconds.
+            is_two_digits_upto(hours, 23)
+                && is_two_digits_upto(minutes, 59)
+                && is_two_digits_upto(seconds, 60)
+        }
+        _ => false,
+    }
+}
+
+/// Check if a string is an ISO 8601 duration like `P1Y2M`, `PT30M` or `P1DT0.5S`
+fn is_duration(value: &str) -> bool {
+    // Count numbers each followed by a designator, which are in the given order
+    fn count_components(s: &str, designators: &str) -> Option<usize> {
+        let mut designators = designators;
+        let mut count = 0;
+        let mut number_len = 0;
+        for c in s.chars() {
+            if c.is_ascii_digit() || c == '.' || c == ',' {
+                number_len += 1;
+            } else {
+                let i = designators.find(c)?;
+                if number_len == 0 {
+                    return None;
+                }
+                designators = &designators[i + 1..];
+                number_len = 0;
+                count += 1;
+            }
+        }
+        (number_len == 0).then_some(count)
+    }
+
+    let Some(rest) = value.strip_prefix('P') else {
+        return false;
+    };
+    let (date, time) = match rest.split_once('T') {
+        Some((date, time)) => (date, Some(time)),
+        None => (rest, None),
+    };
+    let Some(date_components) = count_components(date, "YMWD") else {
+        return false;
+    };
+    match time {
+        Some(time) => count_components(time, "HMS").is_some_and(|count| count > 0),
+        None => date_components > 0,
+    }
+}
+
+/// Check if a string is a number with a fractional part, e.g. `12.50` or `-0.5`
+///
+/// Versions like `1.0` or `2.10` look just the same, so an unsigned number is only taken as a
+/// decimal if its fractional part has more than two digits, or two digits after more than one.
+fn is_decimal(value: &str) -> bool {
+    let (is_signed, value) = match value.strip_prefix('-') {
+        Some(value) => (true, value),
+        None => (false, value),
+    };
+    value.split_once('.').is_some_and(|(integer, fraction)| {
+        !integer.is_empty()
+            && !fraction.is_empty()
+            && integer.bytes().all(|b| b.is_ascii_digit())
+            && fraction.bytes().all(|b| b.is_ascii_digit())
+            && (is_signed || fraction.len() > 2 || (fraction.len() == 2 && integer.len() > 1))
+    })
+}
+
+/// Check if a string is like `someone@example.com`
+fn is_email(value: &str) -> bool {
+    if value.contains(char::is_whitespace) {
+        return false;
+    }
+    value.split_once('@').is_some_and(|(local, domain)| {
+        let labels: Vec<&str> = domain.split('.').collect();
+        !local.is_empty()
+            && labels.len() > 1
+            && labels.iter().all(|label| {
+                !label.is_empty() && label.chars().all(|c| c.is_alphanumeric() || c == '-')
+            })
+            && labels
+                .last()
+                .is_some_and(|tld| tld.len() > 1 && tld.chars().all(char::is_alphabetic))
+    })
+}
+
+/// Check if a string is an absolute URL like `https://example.com/`
+fn is_url(value: &str) -> bool {
+    if value.contains(char::is_whitespace) {
+        return false;
+    }
+    value.split_once("://").is_some_and(|(scheme, rest)| {
+        let mut chars = scheme.chars();
+        chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+            && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+            && !rest.is_empty()
+    })
+}
+
+/// Check if a string looks like Base64-encoded binary data
+///
+/// As lots of ordinary words are valid Base64 as well, only long enough strings with a mix of
+/// upper case letters, lower case letters and digits are considered. Alphanumeric IDs and tokens
+/// are mixed so too, hence a `+`, `/` or padding `=` is required as well.
+fn is_base64(value: &str) -> bool {
+    let data = value.trim_end_matches('=');
+    value.len() >= 20
+        && value.bytes().any(|b| matches!(b, b'+' | b'/' | b'='))
+        && value.len().is_multiple_of(4)
+        && value.len() - data.len() <= 2
+        && data
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'+' || b == b'/')
+        && data.bytes().any(|b| b.is_ascii_uppercase())
+        && data.bytes().any(|b| b.is_ascii_lowercase())
+        && data.bytes().any(|b| b.is_ascii_digit())
+}
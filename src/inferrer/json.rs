@@ -1,59 +1,350 @@
 /// Infer a schema from a given JSONValue
 use indexmap::IndexMap;
 use inflector::Inflector;
-use iso8601::datetime as parse_iso8601_datetime;
+use iso8601::{date as parse_iso8601_date, datetime as parse_iso8601_datetime};
+use itertools::Itertools;
 use serde_json::Value as JSONValue;
 use uuid::Uuid;
 
+use std::collections::HashSet;
+
 // use crate::mapset_impl::Map;
-use super::unioner::union;
-use crate::schema::{ArenaIndex, ITypeArena, Map, NameHints, Schema, Type, TypeArena};
+use super::format;
+use super::unioner::{are_heterogeneous, Kind, UnionerClosure};
+use crate::error::{Error, Result};
+use crate::schema::{
+    ArenaIndex, Enum, ITypeArena, Map, NameHints, Number, Primitive, Schema, Type, TypeArena,
+};
+
+/// The maximum length of arrays to be detected as tuples
+///
+/// Longer arrays with elements of different kinds are arrays of unions, as they are unlikely to
+/// be fixed-length records. It is also what keeps tuple detection the same when streaming, where
+/// long arrays are unioned chunk by chunk.
+pub(super) const MAX_TUPLE_LEN: usize = 8;
 
 /// Infer a `Schema` from a `JSONValue`
-pub fn infer(json: &JSONValue, root_name: Option<String>) -> Schema {
-    InferrerClosure::new().run(json, root_name)
+pub fn infer(json: &JSONValue, root_name: Option<String>) -> Result<Schema> {
+    InferrerClosure::new(&JSONInferrer::default()).run(json, root_name)
+}
+
+/// Infer a single `Schema` from multiple `JSONValue` samples by unioning their root types
+///
+/// Fields absent from some of the samples are marked as `Missing`, just as they would be in an
+/// array of objects.
+pub fn infer_many<'a>(
+    jsons: impl IntoIterator<Item = &'a JSONValue>,
+    root_name: Option<String>,
+) -> Result<Schema> {
+    InferrerClosure::new(&JSONInferrer::default()).run_many(jsons, root_name)
+}
+
+/// An inferrer that infers a `Schema` from `JSONValue`s as configured
+///
+/// Unlike [`infer`] and [`infer_many`], it allows to enable optional detections.
+#[derive(Debug, Default)]
+pub struct JSONInferrer {
+    /// Detect low-cardinality strings as [`Type::Enum`] if set
+    pub enum_detection: Option<EnumDetection>,
+    /// Detect short fixed-length arrays with elements of different kinds as [`Type::Tuple`]
+    pub to_detect_tuples: bool,
+    /// Detect dictionary-style objects (e.g. keyed by IDs) as [`Type::Dict`] if set
+    pub dict_detection: Option<DictDetection>,
+    /// Keep objects tagged by a field (e.g. `"type": "click"`) apart as variants of a union if set
+    pub tagged_union_detection: Option<TaggedUnionDetection>,
+    /// Detect strings of dates, times, durations, URLs, emails, IP addresses, decimal numbers and
+    /// Base64 data, in addition to date-times and UUIDs which are always detected
+    pub to_detect_string_formats: bool,
+    /// Record the range and the integrality of numbers at every position as [`Type::Number`]
+    pub to_track_number_ranges: bool,
+}
+
+impl JSONInferrer {
+    pub fn new_default() -> JSONInferrer {
+        JSONInferrer {
+            enum_detection: Some(EnumDetection::default()),
+            to_detect_tuples: true,
+            dict_detection: Some(DictDetection::default()),
+            tagged_union_detection: Some(TaggedUnionDetection::default()),
+            to_detect_string_formats: true,
+            to_track_number_ranges: true,
+        }
+    }
+
+    /// Infer a `Schema` from a `JSONValue`
+    pub fn infer(&self, json: &JSONValue, root_name: Option<String>) -> Result<Schema> {
+        InferrerClosure::new(self).run(json, root_name)
+    }
+
+    /// Infer a single `Schema` from multiple `JSONValue` samples by unioning their root types
+    pub fn infer_many<'a>(
+        &self,
+        jsons: impl IntoIterator<Item = &'a JSONValue>,
+        root_name: Option<String>,
+    ) -> Result<Schema> {
+        InferrerClosure::new(self).run_many(jsons, root_name)
+    }
+}
+
+/// Thresholds to determine whether strings at a position are values of an enum
+///
+/// All strings at the same position (e.g. a field of objects in an array, or in multiple samples)
+/// are collected during inference. Only if their distinct values are few enough compared to the
+/// total number of occurrences, is an enum kept. O.W., they are just treated as `String`.
+#[derive(Debug, Clone)]
+pub struct EnumDetection {
+    /// The maximum number of distinct values
+    pub max_variants: usize,
+    /// The minimum number of occurrences of strings
+    pub min_occurrences: usize,
+    /// The maximum ratio of the number of distinct values to the number of occurrences
+    pub max_variant_ratio: f64,
+}
+
+impl Default for EnumDetection {
+    fn default() -> Self {
+        EnumDetection {
+            max_variants: 16,
+            min_occurrences: 3,
+            max_variant_ratio: 0.5,
+        }
+    }
+}
+
+impl EnumDetection {
+    /// Check if an enum candidate collected during inference meets the thresholds
+    pub fn accepts(&self, r#enum: &Enum) -> bool {
+        let variants = r#enum.variants.len();
+        let occurrences = r#enum.occurrences();
+        variants <= self.max_variants
+            && occurrences >= self.min_occurrences
+            && variants as f64 <= self.max_variant_ratio * occurrences as f64
+    }
+}
+
+/// Thresholds to determine whether an object is dictionary-style
+///
+/// An object is always treated as a dictionary if all its keys look like IDs (i.e. numbers, UUIDs
+/// or dates). O.W., it is only treated as a dictionary if it has a lot of keys and its values are
+/// all alike.
+#[derive(Debug, Clone)]
+pub struct DictDetection {
+    /// The minimum number of keys of an object with values all alike
+    pub min_keys: usize,
+}
+
+impl Default for DictDetection {
+    fn default() -> Self {
+        DictDetection { min_keys: 20 }
+    }
+}
+
+impl DictDetection {
+    /// Check if an object is dictionary-style
+    pub fn accepts(&self, map: &serde_json::Map<String, JSONValue>) -> bool {
+        self.accepts_parts(
+            &map.keys().collect::<Vec<_>>(),
+            map.values().map(|value| match *value {
+                JSONValue::Null => None,
+                JSONValue::Bool(_) => Some(Kind::Bool),
+                JSONValue::Number(_) => Some(Kind::Number),
+                JSONValue::String(_) => Some(Kind::String),
+                JSONValue::Array(_) => Some(Kind::Array),
+                JSONValue::Object(_) => Some(Kind::Map),
+            }),
+        )
+    }
+
+    /// Check if an object is dictionary-style given its keys and the kinds of its values, where
+    /// `None` goes with any kind
+    pub(super) fn accepts_parts(
+        &self,
+        keys: &[&String],
+        kinds: impl Iterator<Item = Option<Kind>>,
+    ) -> bool {
+        if keys.is_empty() {
+            return false;
+        }
+        let is_id_like = keys
+            .iter()
+            .all(|key| !key.is_empty() && key.chars().all(|c| c.is_ascii_digit()))
+            || keys.iter().all(|key| Uuid::parse_str(key).is_ok())
+            || keys
+                .iter()
+                .all(|key| parse_iso8601_datetime(key).is_ok() || parse_iso8601_date(key).is_ok());
+        let are_values_alike = || kinds.flatten().all_equal();
+        is_id_like || (keys.len() >= self.min_keys && are_values_alike())
+    }
+}
+
+/// Thresholds to determine whether objects at the same position are variants of a tagged union
+///
+/// Objects are normally merged into one with fields absent from some of them marked as optional.
+/// But if they share a field of strings with only a few distinct values (i.e. tags), and objects
+/// with different tags differ in structure, one type is kept for each tag instead.
+#[derive(Debug, Clone)]
+pub struct TaggedUnionDetection {
+    /// The maximum number of distinct tags
+    pub max_tags: usize,
+}
+
+impl Default for TaggedUnionDetection {
+    fn default() -> Self {
+        TaggedUnionDetection { max_tags: 8 }
+    }
+}
+
+/// Get the type of a string regardless of its possible enum values
+fn infer_string_type(value: &str, to_detect_formats: bool) -> Primitive {
+    if parse_iso8601_datetime(value).is_ok() {
+        Primitive::Date
+    } else if Uuid::parse_str(value).is_ok() {
+        Primitive::UUID
+    } else if to_detect_formats {
+        format::detect(value).unwrap_or(Primitive::String)
+    } else {
+        Primitive::String
+    }
 }
 
 // struct SchemaInferer {/* ... */}
 
 /// An closure for the inferrer to work
-struct InferrerClosure {
+pub(super) struct InferrerClosure<'a> {
     arena: TypeArena,
+    options: &'a JSONInferrer,
 }
 
-impl InferrerClosure {
-    fn new() -> Self {
+impl<'a> InferrerClosure<'a> {
+    pub(super) fn new(options: &'a JSONInferrer) -> Self {
         let arena = TypeArena::new();
-        InferrerClosure { arena }
+        InferrerClosure { arena, options }
+    }
+
+    fn run(mut self, json: &JSONValue, root_name: Option<String>) -> Result<Schema> {
+        let root = self.rinfer(json, root_name)?;
+        Ok(self.finish(root))
+    }
+
+    fn run_many<'j>(
+        mut self,
+        jsons: impl IntoIterator<Item = &'j JSONValue>,
+        root_name: Option<String>,
+    ) -> Result<Schema> {
+        let roots: Vec<ArenaIndex> = jsons
+            .into_iter()
+            .map(|json| self.rinfer(json, root_name.clone()))
+            .collect::<Result<_>>()?;
+        if roots.is_empty() {
+            return Err(Error::NoSamples);
+        }
+        let root = self.union(roots)?;
+        Ok(self.finish(root))
     }
 
-    fn run(mut self, json: &JSONValue, root_name: Option<String>) -> Schema {
-        let root = self.rinfer(json, root_name);
+    /// Finalize enums and take the arena out as a `Schema` with the given root
+    pub(super) fn finish(mut self, root: ArenaIndex) -> Schema {
+        let root = self.finalize_enums(root);
 
         let arena = self.arena;
         Schema { arena, root }
     }
 
-    fn rinfer(&mut self, json: &JSONValue, outer_name: Option<String>) -> ArenaIndex {
-        match *json {
-            JSONValue::Number(ref number) => {
-                if number.is_f64() {
-                    self.arena.get_index_of_primitive(Type::Float)
-                } else {
-                    self.arena.get_index_of_primitive(Type::Int)
+    /// Check if strings are collected as enum candidates during inference
+    fn collects_strings(&self) -> bool {
+        self.options.enum_detection.is_some() || self.options.tagged_union_detection.is_some()
+    }
+
+    /// The maximum number of variants of an enum candidate that might still be accepted, if strings
+    /// are collected
+    ///
+    /// Only tags, each of which is a single variant, are kept without enum detection.
+    fn max_enum_variants(&self) -> Option<usize> {
+        self.collects_strings().then(|| {
+            self.options
+                .enum_detection
+                .as_ref()
+                .map_or(1, |enum_detection| enum_detection.max_variants.max(1))
+        })
+    }
+
+    /// Union types, with tagged unions detected as configured
+    pub(super) fn union(&mut self, types: Vec<ArenaIndex>) -> Result<ArenaIndex> {
+        let max_enum_variants = self.max_enum_variants();
+        UnionerClosure::new(&mut self.arena)
+            .detecting_tagged_unions(self.options.tagged_union_detection.as_ref())
+            .collapsing_enums(max_enum_variants)
+            .union(types)
+    }
+
+    /// Replace all enum candidates not meeting the thresholds with `String` and return the
+    /// possibly replaced root
+    ///
+    /// Tags of tagged unions are always kept, regardless of the thresholds.
+    fn finalize_enums(&mut self, root: ArenaIndex) -> ArenaIndex {
+        if !self.collects_strings() {
+            return root;
+        }
+        let tags: HashSet<ArenaIndex> = self
+            .arena
+            .iter()
+            .filter_map(|(_, r#type)| {
+                let union = r#type.as_union()?;
+                let key = union.discriminator(&self.arena)?;
+                Some(
+                    union
+                        .types
+                        .iter()
+                        .filter_map(|&arni| self.arena.get(arni).unwrap().as_map())
+                        .map(|map| map.fields[key])
+                        .collect::<Vec<_>>(),
+                )
+            })
+            .flatten()
+            .collect();
+        let rejected: HashSet<ArenaIndex> = self
+            .arena
+            .iter()
+            .filter_map(|(arni, r#type)| match r#type.as_enum() {
+                Some(_) if tags.contains(&arni) => None,
+                Some(r#enum)
+                    if !self
+                        .options
+                        .enum_detection
+                        .as_ref()
+                        .is_some_and(|enum_detection| enum_detection.accepts(r#enum)) =>
+                {
+                    Some(arni)
                 }
+                _ => None,
+            })
+            .collect();
+        let string = self.arena.get_index_of_primitive(Primitive::String);
+        let replace = |arni| {
+            if rejected.contains(&arni) {
+                string
+            } else {
+                arni
             }
-            JSONValue::Bool(_) => self.arena.get_index_of_primitive(Type::Bool),
-            JSONValue::String(ref value) => {
-                if parse_iso8601_datetime(value).is_ok() {
-                    self.arena.get_index_of_primitive(Type::Date)
-                } else if Uuid::parse_str(value).is_ok() {
-                    self.arena.get_index_of_primitive(Type::UUID)
-                } else {
-                    self.arena.get_index_of_primitive(Type::String)
-                }
+        };
+        for (_, r#type) in self.arena.iter_mut() {
+            r#type.replace_inner_types(replace);
+        }
+        for &arni in rejected.iter() {
+            self.arena.remove(arni);
+        }
+        replace(root)
+    }
+
+    fn rinfer(&mut self, json: &JSONValue, outer_name: Option<String>) -> Result<ArenaIndex> {
+        Ok(match *json {
+            JSONValue::Number(ref number) => {
+                // `as_f64` never fails without the `arbitrary_precision` feature of `serde_json`.
+                self.infer_number(number.as_f64().unwrap(), !number.is_f64())
             }
-            JSONValue::Null => self.arena.get_index_of_primitive(Type::Null),
+            JSONValue::Bool(_) => self.arena.get_index_of_primitive(Primitive::Bool),
+            JSONValue::String(ref value) => self.infer_string(value, outer_name),
+            JSONValue::Null => self.arena.get_index_of_primitive(Primitive::Null),
             JSONValue::Array(ref array) => {
                 let mut types = vec![];
 
@@ -75,25 +366,189 @@ impl InferrerClosure {
                 for value in array.iter() {
                     // In the current implementation, every union will have at most one map inside.
                     // So there would be no name collision for now.
-                    types.push(self.rinfer(value, inner_name.clone()))
+                    types.push(self.rinfer(value, inner_name.clone())?)
                 }
-                let inner = union(&mut self.arena, types); // FIX: union name
-                self.arena.insert(Type::Array(inner))
+                self.infer_array(types)?
+            }
+            JSONValue::Object(ref map)
+                if self
+                    .options
+                    .dict_detection
+                    .as_ref()
+                    .is_some_and(|dict_detection| dict_detection.accepts(map)) =>
+            {
+                // Values are named as if they were elements of an array.
+                let inner_name = outer_name.map(|outer_name| outer_name.to_singular());
+                let values: Vec<ArenaIndex> = map
+                    .values()
+                    .map(|value| self.rinfer(value, inner_name.clone()))
+                    .collect::<Result<_>>()?;
+                self.infer_dict(map.keys(), values)?
             }
             JSONValue::Object(ref map) => {
                 let mut fields = IndexMap::new();
                 for (key, value) in map.iter() {
                     fields.insert(
                         key.to_owned(),
-                        self.rinfer(value, Some(key.to_pascal_case())),
+                        self.rinfer(value, Some(key.to_pascal_case()))?,
                     );
                 }
+                self.infer_map(fields, outer_name)
+            }
+        })
+    }
+
+    /// Get the index of a primitive type in the arena
+    pub(super) fn primitive(&self, primitive: Primitive) -> ArenaIndex {
+        self.arena.get_index_of_primitive(primitive)
+    }
+
+    /// Insert a type into the arena
+    pub(super) fn insert(&mut self, r#type: Type) -> ArenaIndex {
+        self.arena.insert(r#type)
+    }
+
+    pub(super) fn infer_number(&mut self, value: f64, is_integer: bool) -> ArenaIndex {
+        if self.options.to_track_number_ranges {
+            self.arena
+                .insert(Type::Number(Number::new(value, is_integer)))
+        } else if is_integer {
+            self.arena.get_index_of_primitive(Primitive::Int)
+        } else {
+            self.arena.get_index_of_primitive(Primitive::Float)
+        }
+    }
+
+    pub(super) fn infer_string(&mut self, value: &str, outer_name: Option<String>) -> ArenaIndex {
+        match infer_string_type(value, self.options.to_detect_string_formats) {
+            Primitive::String if self.collects_strings() => {
+                // Every string is an enum candidate with a single variant for now. They are
+                // unioned and then finalized as a whole after inference.
                 let mut name_hints = NameHints::new();
                 if let Some(outer_name) = outer_name {
                     name_hints.insert(outer_name);
                 }
-                self.arena.insert(Type::Map(Map { name_hints, fields }))
+                let mut variants = IndexMap::new();
+                variants.insert(value.to_owned(), 1);
+                self.arena.insert(Type::Enum(Enum {
+                    name_hints,
+                    name: None,
+                    variants,
+                }))
+            }
+            primitive => self.arena.get_index_of_primitive(primitive),
+        }
+    }
+
+    /// Infer an array, or a tuple if detected, from the types of all its elements
+    pub(super) fn infer_array(&mut self, types: Vec<ArenaIndex>) -> Result<ArenaIndex> {
+        Ok(
+            if self.options.to_detect_tuples
+                && types.len() > 1
+                && types.len() <= MAX_TUPLE_LEN
+                && are_heterogeneous(&self.arena, &types)
+            {
+                // e.g. `[1.5, 2.5, "label"]`, which is then checked against other samples at the
+                // same position (if any) during unioning
+                self.arena.insert(Type::Tuple(types))
+            } else {
+                let inner = self.union(types)?; // FIX: union name
+                self.arena.insert(Type::Array(inner))
+            },
+        )
+    }
+
+    fn infer_dict<'k>(
+        &mut self,
+        keys: impl Iterator<Item = &'k String>,
+        values: Vec<ArenaIndex>,
+    ) -> Result<ArenaIndex> {
+        let keys: Vec<ArenaIndex> = keys
+            .map(|key| {
+                let primitive = infer_string_type(key, self.options.to_detect_string_formats);
+                self.arena.get_index_of_primitive(primitive)
+            })
+            .collect();
+        let key = self.union(keys)?;
+        let value = self.union(values)?;
+        Ok(self.arena.insert(Type::Dict(key, value)))
+    }
+
+    fn infer_map(
+        &mut self,
+        fields: IndexMap<String, ArenaIndex>,
+        outer_name: Option<String>,
+    ) -> ArenaIndex {
+        let mut name_hints = NameHints::new();
+        if let Some(outer_name) = outer_name {
+            name_hints.insert(outer_name);
+        }
+        self.arena.insert(Type::Map(Map {
+            name_hints,
+            name: None,
+            fields,
+        }))
+    }
+
+    /// Infer a map, or a dict if detected, from the types of all its fields inferred in advance
+    ///
+    /// Unlike [`rinfer`](Self::rinfer), it does not require the whole object to be known before
+    /// its values are inferred.
+    pub(super) fn infer_object(
+        &mut self,
+        fields: IndexMap<String, ArenaIndex>,
+        outer_name: Option<String>,
+    ) -> Result<ArenaIndex> {
+        let is_dict = self
+            .options
+            .dict_detection
+            .as_ref()
+            .is_some_and(|dict_detection| {
+                dict_detection.accepts_parts(
+                    &fields.keys().collect::<Vec<_>>(),
+                    fields
+                        .values()
+                        .map(|&arni| self.arena.get(arni).and_then(Kind::of)),
+                )
+            });
+        if !is_dict {
+            return Ok(self.infer_map(fields, outer_name));
+        }
+        // Values are named as if they were elements of an array, instead of by their keys.
+        let inner_name = outer_name.map(|outer_name| outer_name.to_singular());
+        for &arni in fields.values() {
+            self.rename(arni, inner_name.clone());
+        }
+        let (keys, values): (Vec<String>, Vec<ArenaIndex>) = fields.into_iter().unzip();
+        self.infer_dict(keys.iter(), values)
+    }
+
+    /// Rename a type just inferred, as if it were inferred with another outer name
+    fn rename(&mut self, arni: ArenaIndex, outer_name: Option<String>) {
+        let singular = || {
+            outer_name
+                .as_ref()
+                .map(|outer_name| outer_name.to_singular())
+        };
+        let (inner_types, inner_name) = match *self.arena.get_mut(arni).unwrap() {
+            Type::Map(Map {
+                ref mut name_hints, ..
+            })
+            | Type::Enum(Enum {
+                ref mut name_hints, ..
+            }) => {
+                name_hints.clear();
+                name_hints.extend(outer_name);
+                return;
             }
+            // Unions of elements of arrays are unnamed, as opposed to their inner types.
+            Type::Union(ref union) => (union.types.iter().cloned().collect(), outer_name.clone()),
+            Type::Array(inner) | Type::Dict(_, inner) => (vec![inner], singular()),
+            Type::Tuple(ref types) => (types.clone(), singular()),
+            _ => return,
+        };
+        for inner in inner_types {
+            self.rename(inner, inner_name.clone());
         }
     }
 }
@@ -0,0 +1,307 @@
+//! Convert a JSON Schema into a schema
+use indexmap::{IndexMap, IndexSet};
+use inflector::Inflector;
+use serde_json::{Map as JSONMap, Value as JSONValue};
+
+use std::collections::{HashMap, HashSet};
+
+use crate::error::{Error, Result};
+use crate::schema::{
+    ArenaIndex, Enum, ITypeArena, Map, NameHints, Primitive, Schema, Type, TypeArena, Union,
+};
+
+/// Convert a JSON Schema document (draft-07 or 2020-12) into a `Schema`
+///
+/// Only local references (e.g. `#/$defs/Node` or `#/definitions/Node`) are supported. Others lead
+/// to [`Error::InvalidReference`]. Keywords without a counterpart in [`Type`] (e.g. `minimum` or
+/// `pattern`) are ignored.
+pub fn infer(json_schema: &JSONValue, root_name: Option<String>) -> Result<Schema> {
+    ConverterClosure::new(json_schema).run(root_name)
+}
+
+/// An closure for the converter to work
+struct ConverterClosure<'a> {
+    arena: TypeArena,
+    document: &'a JSONValue,
+    /// Types of references that have been resolved (or are being resolved)
+    refs: HashMap<String, ArenaIndex>,
+}
+
+impl<'a> ConverterClosure<'a> {
+    fn new(document: &'a JSONValue) -> Self {
+        ConverterClosure {
+            arena: TypeArena::new(),
+            document,
+            refs: HashMap::new(),
+        }
+    }
+
+    fn run(mut self, root_name: Option<String>) -> Result<Schema> {
+        // The root is resolved as a reference so that recursive `"$ref": "#"` works.
+        let root = self.resolve("#", root_name)?;
+
+        let arena = self.arena;
+        Ok(Schema { arena, root })
+    }
+
+    /// Resolve a local reference, converting its target if not yet
+    fn resolve(&mut self, reference: &str, outer_name: Option<String>) -> Result<ArenaIndex> {
+        if let Some(&arni) = self.refs.get(reference) {
+            return Ok(arni);
+        }
+        let document = self.document;
+        let target = match reference
+            .strip_prefix('#')
+            .and_then(|pointer| document.pointer(pointer))
+        {
+            Some(target) => target,
+            // TODO: remote references and `$anchor`s are not supported for now
+            None => return Err(Error::InvalidReference(reference.to_owned())),
+        };
+        // e.g. `Node` for `#/$defs/Node`
+        let name = match reference.rsplit_once('/') {
+            Some((_, name)) if !name.is_empty() => {
+                Some(name.replace("~1", "/").replace("~0", "~").to_pascal_case())
+            }
+            _ => outer_name,
+        };
+
+        // Recursive references made during the conversion point to the placeholder for now.
+        let placeholder = self.arena.insert(Type::Any);
+        self.refs.insert(reference.to_owned(), placeholder);
+        let arni = self.rconvert(target, name)?;
+        self.refs.insert(reference.to_owned(), arni);
+        for (_, r#type) in self.arena.iter_mut() {
+            r#type.replace_inner_types(|r#type| if r#type == placeholder { arni } else { r#type });
+        }
+        self.arena.remove(placeholder);
+        Ok(arni)
+    }
+
+    fn rconvert(&mut self, node: &JSONValue, outer_name: Option<String>) -> Result<ArenaIndex> {
+        let object = match *node {
+            JSONValue::Object(ref object) => object,
+            // `true` accepts anything, while `false` accepts nothing which is not expressible.
+            _ => return Ok(self.arena.get_index_of_primitive(Primitive::Any)),
+        };
+        let name = object
+            .get("title")
+            .and_then(JSONValue::as_str)
+            .map(|title| title.to_pascal_case())
+            .or(outer_name);
+
+        if let Some(reference) = object.get("$ref").and_then(JSONValue::as_str) {
+            return self.resolve(reference, name);
+        }
+        if let Some(values) = object.get("enum").and_then(JSONValue::as_array) {
+            return Ok(self.convert_values(values, name));
+        }
+        if let Some(value) = object.get("const") {
+            return Ok(self.convert_values(std::slice::from_ref(value), name));
+        }
+        for keyword in ["oneOf", "anyOf"] {
+            if let Some(schemas) = object.get(keyword).and_then(JSONValue::as_array) {
+                let types = schemas
+                    .iter()
+                    .map(|schema| self.rconvert(schema, name.clone()))
+                    .collect::<Result<_>>()?;
+                return Ok(self.union(types, name));
+            }
+        }
+
+        let type_names: Vec<&str> = match object.get("type") {
+            Some(JSONValue::String(type_name)) => vec![type_name],
+            Some(JSONValue::Array(type_names)) => {
+                type_names.iter().filter_map(JSONValue::as_str).collect()
+            }
+            _ if object.contains_key("properties")
+                || object.contains_key("additionalProperties") =>
+            {
+                vec!["object"]
+            }
+            _ if object.contains_key("items") || object.contains_key("prefixItems") => {
+                vec!["array"]
+            }
+            _ => vec![],
+        };
+        if type_names.is_empty() {
+            return Ok(self.arena.get_index_of_primitive(Primitive::Any));
+        }
+        let types = type_names
+            .into_iter()
+            .map(|type_name| self.convert_type(object, type_name, name.clone()))
+            .collect::<Result<_>>()?;
+        Ok(self.union(types, name))
+    }
+
+    /// Convert a schema of a specific `type`
+    fn convert_type(
+        &mut self,
+        object: &JSONMap<String, JSONValue>,
+        type_name: &str,
+        name: Option<String>,
+    ) -> Result<ArenaIndex> {
+        Ok(match type_name {
+            "null" => self.arena.get_index_of_primitive(Primitive::Null),
+            "boolean" => self.arena.get_index_of_primitive(Primitive::Bool),
+            "integer" => self.arena.get_index_of_primitive(Primitive::Int),
+            "number" => self.arena.get_index_of_primitive(Primitive::Float),
+            "string" => self.arena.get_index_of_primitive(
+                match object.get("format").and_then(JSONValue::as_str) {
+                    Some("date-time") => Primitive::Date,
+                    Some("uuid") => Primitive::UUID,
+                    Some("date") => Primitive::PlainDate,
+                    Some("time") => Primitive::Time,
+                    Some("duration") => Primitive::Duration,
+                    Some("uri") => Primitive::URL,
+                    Some("email") => Primitive::Email,
+                    Some("ipv4" | "ipv6") => Primitive::IPAddress,
+                    Some("decimal") => Primitive::Decimal,
+                    _ => match object.get("contentEncoding").and_then(JSONValue::as_str) {
+                        Some("base64") => Primitive::Bytes,
+                        _ => Primitive::String,
+                    },
+                },
+            ),
+            "array" => {
+                let inner_name = name.map(|name| name.to_singular());
+                // `prefixItems` since 2020-12, or `items` being an array before that
+                let prefix_items = object
+                    .get("prefixItems")
+                    .or_else(|| object.get("items").filter(|items| items.is_array()));
+                // An empty tuple is no valid type in targets, so it is taken as an array instead.
+                if let Some(JSONValue::Array(schemas)) = prefix_items.filter(|schemas| {
+                    schemas
+                        .as_array()
+                        .is_some_and(|schemas| !schemas.is_empty())
+                }) {
+                    let types = schemas
+                        .iter()
+                        .map(|schema| self.rconvert(schema, inner_name.clone()))
+                        .collect::<Result<_>>()?;
+                    return Ok(self.arena.insert(Type::Tuple(types)));
+                }
+                let inner = match object.get("items") {
+                    Some(items) if !items.is_array() => self.rconvert(items, inner_name)?,
+                    _ => self.arena.get_index_of_primitive(Primitive::Any),
+                };
+                self.arena.insert(Type::Array(inner))
+            }
+            "object" => match (
+                object
+                    .get("properties")
+                    .and_then(JSONValue::as_object)
+                    .filter(|properties| !properties.is_empty()),
+                object.get("additionalProperties"),
+            ) {
+                (None, Some(additional_properties @ JSONValue::Object(_))) => {
+                    // Values are named as if they were elements of an array.
+                    let inner_name = name.map(|name| name.to_singular());
+                    let key = self.arena.get_index_of_primitive(Primitive::String);
+                    let value = self.rconvert(additional_properties, inner_name)?;
+                    self.arena.insert(Type::Dict(key, value))
+                }
+                // Without any property, it is taken as a dict of anything instead of an empty map,
+                // which is no valid class in targets.
+                (None, _) => {
+                    let key = self.arena.get_index_of_primitive(Primitive::String);
+                    let value = self.arena.get_index_of_primitive(Primitive::Any);
+                    self.arena.insert(Type::Dict(key, value))
+                }
+                (properties, _) => {
+                    let required: HashSet<&str> = object
+                        .get("required")
+                        .and_then(JSONValue::as_array)
+                        .map(|required| required.iter().filter_map(JSONValue::as_str).collect())
+                        .unwrap_or_default();
+                    let mut fields = IndexMap::new();
+                    for (key, schema) in properties.into_iter().flatten() {
+                        let field_name = Some(key.to_pascal_case());
+                        let mut r#type = self.rconvert(schema, field_name.clone())?;
+                        if !required.contains(key.as_str()) {
+                            let missing = self.arena.get_index_of_primitive(Primitive::Missing);
+                            r#type = self.union(vec![r#type, missing], field_name);
+                        }
+                        fields.insert(key.to_owned(), r#type);
+                    }
+                    let mut name_hints = NameHints::new();
+                    if let Some(name) = name {
+                        name_hints.insert(name);
+                    }
+                    self.arena.insert(Type::Map(Map {
+                        name_hints,
+                        name: None,
+                        fields,
+                    }))
+                }
+            },
+            _ => self.arena.get_index_of_primitive(Primitive::Any),
+        })
+    }
+
+    /// Convert values of `enum` or `const`
+    ///
+    /// String values are collected into a [`Type::Enum`], while other values are taken as their
+    /// types.
+    fn convert_values(&mut self, values: &[JSONValue], name: Option<String>) -> ArenaIndex {
+        let mut variants = IndexMap::new();
+        let mut types = vec![];
+        for value in values.iter() {
+            let primitive = match *value {
+                JSONValue::String(ref value) => {
+                    variants.insert(value.to_owned(), 1);
+                    continue;
+                }
+                JSONValue::Null => Primitive::Null,
+                JSONValue::Bool(_) => Primitive::Bool,
+                JSONValue::Number(ref number) if number.is_f64() => Primitive::Float,
+                JSONValue::Number(_) => Primitive::Int,
+                JSONValue::Array(_) | JSONValue::Object(_) => Primitive::Any,
+            };
+            types.push(self.arena.get_index_of_primitive(primitive));
+        }
+        if !variants.is_empty() {
+            let mut name_hints = NameHints::new();
+            if let Some(ref name) = name {
+                name_hints.insert(name.to_owned());
+            }
+            types.push(self.arena.insert(Type::Enum(Enum {
+                name_hints,
+                name: None,
+                variants,
+            })));
+        }
+        self.union(types, name)
+    }
+
+    /// Make a union of types, with inner unions flattened
+    ///
+    /// Unlike unioning in the JSON inferrer, maps are kept as is instead of being merged, as they
+    /// are explicitly declared as alternatives.
+    fn union(&mut self, types: Vec<ArenaIndex>, name: Option<String>) -> ArenaIndex {
+        let mut inner_types = IndexSet::new();
+        for r#type in types.into_iter() {
+            match *self.arena.get(r#type).unwrap() {
+                Type::Union(ref union) => inner_types.extend(union.types.iter().cloned()),
+                _ => {
+                    inner_types.insert(r#type);
+                }
+            }
+        }
+        match inner_types.len() {
+            0 => self.arena.get_index_of_primitive(Primitive::Any),
+            1 => inner_types.into_iter().next().unwrap(),
+            _ => {
+                let mut name_hints = NameHints::new();
+                if let Some(name) = name {
+                    name_hints.insert(name);
+                }
+                self.arena.insert(Type::Union(Union {
+                    name_hints,
+                    name: None,
+                    types: inner_types,
+                }))
+            }
+        }
+    }
+}
@@ -1,6 +1,16 @@
+mod format;
 mod json;
+mod json_schema;
 mod optimizer;
-mod unioner;
+mod renamer;
+mod stream;
+pub(crate) mod unioner;
 
-pub use json::infer as infer_from_json;
-pub use optimizer::Optimizer;
+pub use json::{
+    infer as infer_from_json, infer_many as infer_from_json_samples, DictDetection, EnumDetection,
+    JSONInferrer, TaggedUnionDetection,
+};
+pub use json_schema::infer as infer_from_json_schema;
+pub use optimizer::{MapSimilarity, Optimizer};
+pub use renamer::Renamer;
+pub use stream::StreamingInferrer;
@@ -1,18 +1,22 @@
 use bidirectional_map::Bimap;
 use disjoint_sets::UnionFind;
+use indexmap::{IndexMap, IndexSet};
+use itertools::Itertools;
 
 use std::{
-    collections::{HashMap, HashSet},
     mem,
     ops::{Deref, DerefMut, Drop},
 };
 
 use super::unioner::union;
-use crate::schema::{ArenaIndex, ITypeArena, Schema, Type, TypeArena};
+use crate::error::{Error, Result};
+use crate::schema::{ArenaIndex, ITypeArena, Map, Primitive, Schema, Type, TypeArena, Union};
 
 /// A optimizer that merge similar `Map`s and/or same `Union`s as configured
 pub struct Optimizer {
     pub to_merge_similar_datatypes: bool,
+    /// How similar `Map`s are to be merged
+    pub map_similarity: MapSimilarity,
     pub to_merge_same_unions: bool,
 }
 
@@ -20,11 +24,12 @@ impl Optimizer {
     pub fn new_default() -> Optimizer {
         Optimizer {
             to_merge_similar_datatypes: true,
+            map_similarity: MapSimilarity::default(),
             to_merge_same_unions: true,
         }
     }
 
-    pub fn optimize(&self, schema: &mut Schema) {
+    pub fn optimize(&self, schema: &mut Schema) -> Result<()> {
         // <del>
         // Note: Merging maps and unions at the same time may have produced results different from
         // seperate merging (find map sets - merge - flatten - find union sets - merge - flatten).
@@ -35,28 +40,175 @@ impl Optimizer {
         // TODO: merge same array?
         schema.root = do_merge(
             schema,
-            schema.arena.find_disjoint_sets(|a, b| {
-                if let (Some(a), Some(b)) = (a.as_map(), b.as_map()) {
-                    self.to_merge_similar_datatypes && a.is_similar_to(b)
-                } else {
-                    false
-                }
-            }),
-        );
+            schema.arena.find_disjoint_sets(
+                // Similar maps share some field names.
+                |r#type| match *r#type {
+                    Type::Map(ref map) if self.to_merge_similar_datatypes => {
+                        map.compared_keys().collect()
+                    }
+                    _ => vec![],
+                },
+                |fields| self.map_similarity.min_shared_fields(fields),
+                |a, b| {
+                    if let (Some(a), Some(b)) = (a.as_map(), b.as_map()) {
+                        self.to_merge_similar_datatypes
+                            && self.map_similarity.accepts(&schema.arena, a, b)
+                    } else {
+                        false
+                    }
+                },
+            ),
+        )?;
+        // Numbers at different positions are told apart only by whether they are integers, as their
+        // ranges can be merged anyway.
+        let inner_types = |union: &Union| -> IndexSet<ArenaIndex> {
+            union
+                .types
+                .iter()
+                .map(|&arni| match schema.arena.get(arni) {
+                    Some(Type::Number(number)) if number.is_integer => {
+                        schema.arena.get_index_of_primitive(Primitive::Int)
+                    }
+                    Some(Type::Number(_)) => schema.arena.get_index_of_primitive(Primitive::Float),
+                    _ => arni,
+                })
+                .collect()
+        };
         schema.root = do_merge(
             schema,
-            schema.arena.find_disjoint_sets(|a, b| {
-                if let (Some(a), Some(b)) = (a.as_union(), b.as_union()) {
-                    self.to_merge_same_unions && (a.types == b.types)
-                } else {
-                    false
-                }
-            }),
-        );
+            schema.arena.find_disjoint_sets(
+                // Same unions share the same set of inner types, regardless of the order.
+                |r#type| match *r#type {
+                    Type::Union(ref union) if self.to_merge_same_unions => {
+                        Some(inner_types(union).into_iter().sorted().collect::<Vec<_>>())
+                    }
+                    _ => None,
+                },
+                |_| 1,
+                |a, b| {
+                    if let (Some(a), Some(b)) = (a.as_union(), b.as_union()) {
+                        self.to_merge_same_unions && (inner_types(a) == inner_types(b))
+                    } else {
+                        false
+                    }
+                },
+            ),
+        )?;
+        Ok(())
     }
 }
 
-fn do_merge(schema: &mut Schema, sets: HashMap<ArenaIndex, HashSet<ArenaIndex>>) -> ArenaIndex {
+/// Parameters to determine whether two `Map`s are similar enough to be merged
+///
+/// Fields are compared by their names and the kinds of their types, so that e.g. `"id": 1` and
+/// `"id": {...}` are not counted as the same field. Their similarity is measured by the
+/// [Tversky index](https://en.wikipedia.org/wiki/Tversky_index).
+#[derive(Debug, Clone)]
+pub struct MapSimilarity {
+    /// The similarity above which two `Map`s are merged
+    pub threshold: f64,
+    /// The weight of fields only in the former `Map`
+    pub alpha: f64,
+    /// The weight of fields only in the latter `Map`
+    pub beta: f64,
+}
+
+impl Default for MapSimilarity {
+    fn default() -> Self {
+        MapSimilarity {
+            threshold: 0.8,
+            alpha: 1.0,
+            beta: 1.0,
+        }
+    }
+}
+
+impl MapSimilarity {
+    /// Check if two `Map`s are similar enough to be merged
+    ///
+    /// `Map`s with different constants (e.g. tags of variants of a tagged union) in a common field
+    /// are never merged.
+    pub fn accepts(&self, arena: &TypeArena, a: &Map, b: &Map) -> bool {
+        !are_tagged_differently(arena, a, b)
+            && a.tversky_index(b, self.alpha, self.beta, |i, j| are_alike(arena, i, j))
+                > self.threshold
+    }
+
+    /// Get the minimum number of fields that a `Map` of `fields` fields shares with any other
+    /// `Map` it is accepted with
+    ///
+    /// As the index exceeds `threshold`, the shared fields `n` satisfy
+    /// `n * (1 - threshold + threshold * (alpha + beta)) > threshold * min(alpha, beta) * fields`
+    /// regardless of the other `Map`.
+    pub fn min_shared_fields(&self, fields: usize) -> usize {
+        let MapSimilarity {
+            threshold,
+            alpha,
+            beta,
+        } = *self;
+        let ratio = threshold * alpha.min(beta) / (1.0 - threshold + threshold * (alpha + beta));
+        if ratio.is_finite() && ratio > 0.0 {
+            // Rounded down slightly, so that floating-point errors never exclude a candidate
+            (ratio * fields as f64 - 1e-9).floor().max(0.0) as usize + 1
+        } else {
+            1
+        }
+    }
+}
+
+/// Check if two types are of the same kind (i.e. object, array, number, string or boolean),
+/// regardless of their inner types
+///
+/// `Null`, `Missing` and `Any` are alike to any type. A `Union` is alike to a type if any of its
+/// inner types is.
+fn are_alike(arena: &TypeArena, a: ArenaIndex, b: ArenaIndex) -> bool {
+    fn kind(r#type: &Type) -> Option<u8> {
+        match *r#type {
+            Type::Map(_) | Type::Dict(_, _) => Some(0),
+            Type::Array(_) | Type::Tuple(_) => Some(1),
+            Type::Int | Type::Float | Type::Number(_) => Some(2),
+            Type::String
+            | Type::Enum(_)
+            | Type::Date
+            | Type::UUID
+            | Type::PlainDate
+            | Type::Time
+            | Type::Duration
+            | Type::URL
+            | Type::Email
+            | Type::IPAddress
+            | Type::Decimal
+            | Type::Bytes => Some(3),
+            Type::Bool => Some(4),
+            Type::Union(_) | Type::Null | Type::Missing | Type::Any => None,
+        }
+    }
+    let kinds = |arni| -> Vec<Option<u8>> {
+        match *arena.get(arni).unwrap() {
+            Type::Union(ref union) => union
+                .types
+                .iter()
+                .map(|&arni| kind(arena.get(arni).unwrap()))
+                .collect(),
+            ref r#type => vec![kind(r#type)],
+        }
+    };
+    let (a, b) = (kinds(a), kinds(b));
+    a.contains(&None) || b.contains(&None) || a.iter().any(|kind| b.contains(kind))
+}
+
+/// Check if two maps have different constants (e.g. tags of variants of a tagged union) in a
+/// common field
+fn are_tagged_differently(arena: &TypeArena, a: &Map, b: &Map) -> bool {
+    a.fields.keys().any(|key| {
+        matches!((a.tag(key, arena), b.tag(key, arena)), (Some((i, _)), Some((j, _))) if i != j)
+    })
+}
+
+fn do_merge(
+    schema: &mut Schema,
+    sets: IndexMap<ArenaIndex, IndexSet<ArenaIndex>>,
+) -> Result<ArenaIndex> {
     let mut ufarena = TypeArenaWithDSU::from_type_arena(&mut schema.arena);
     for (leader, mut set) in sets.into_iter() {
         set.insert(leader); // leader in disjoint set is now a follower
@@ -69,13 +221,16 @@ fn do_merge(schema: &mut Schema, sets: HashMap<ArenaIndex, HashSet<ArenaIndex>>)
             .filter(|&r#type| ufarena.contains(r#type))
             .collect::<Vec<ArenaIndex>>();
         // unioned is now the new leader
-        let _leader = union(&mut ufarena, compact_set);
+        let _leader = union(&mut ufarena, compact_set)?;
         // References to non-representative AreneIndex will be replaced automatically
         // when TypeArenaWithDSU is dropped
     }
     // Although unioner always keeps the first map slot intact, there is no guarantee that
     // root would always be the first map in types to be unioned. So update it if necessary.
-    ufarena.find_representative(schema.root).unwrap()
+    let root = schema.root;
+    ufarena
+        .find_representative(root)
+        .ok_or_else(|| Error::MalformedSchema(format!("root {:?} is absent from the arena", root)))
     // arena.flatten();
 }
 
@@ -115,8 +270,6 @@ impl<'a> TypeArenaWithDSU<'a> {
     /// representative one in the DSU. This method is invoked automatically upon dropping to ensure
     /// the released `TypeArena` has all its references consistent.
     fn flatten(&mut self) {
-        let mut dangling_types = HashSet::new();
-
         // There might be new types which internally references to non-representative and hence
         // non-existing types. They also need updating. So just iterate over the whole arena
         // instead of just imap which contains no newly inserted types.
@@ -126,12 +279,6 @@ impl<'a> TypeArenaWithDSU<'a> {
         // <del>Only check maps in DSU, as there are newly added types during unioning.</del>
         // Maps not
         for arni in arnis {
-            let arnr = self.find_representative(arni);
-            if arnr.is_some() && arnr.unwrap() != arni {
-                // If it is not a new type (already in the DSU before) and it is non-representative.
-                //// TODO: shoule replace inner type references in a representative type? // FIX
-                dangling_types.insert(arni);
-            }
             //// Unions might be removed during unioning. So if a representative type is not
             //// there anymore, just ignore it for now.
             if let Some(r#type) = self.get_mut(arni) {
@@ -158,15 +305,23 @@ impl<'a> TypeArenaWithDSU<'a> {
                     let inner = mem::take(r#type).into_array().unwrap();
                     *self.get_mut(arni).unwrap() =
                         Type::Array(self.find_representative(inner).unwrap_or(inner));
+                } else if r#type.is_dict() {
+                    let (key, value) = mem::take(r#type).into_dict().unwrap();
+                    *self.get_mut(arni).unwrap() = Type::Dict(
+                        self.find_representative(key).unwrap_or(key),
+                        self.find_representative(value).unwrap_or(value),
+                    );
+                } else if r#type.is_tuple() {
+                    let types = mem::take(r#type).into_tuple().unwrap();
+                    *self.get_mut(arni).unwrap() = Type::Tuple(
+                        types
+                            .into_iter()
+                            .map(|arni| self.find_representative(arni).unwrap_or(arni))
+                            .collect(),
+                    );
                 }
             }
         }
-        for r#type in dangling_types.into_iter() {
-            // TODO: Should these all removed during unioning?
-            println!("removed dangling: {:?}", r#type);
-            // FIXME: temporary fix for #8
-            // assert!(self.arena.remove(r#type).is_none());
-        }
     }
 
     #[inline(always)]
@@ -216,7 +371,16 @@ impl<'a> ITypeArena for TypeArenaWithDSU<'a> {
     }
 
     /// Remove the type denoted by the index i and union i into j in the DSU
+    ///
+    /// If j is the shared slot of a primitive type, i is just replaced with the primitive type
+    /// instead, as the shared slot might not be the representative after the DSU union.
     fn remove_in_favor_of(&mut self, i: ArenaIndex, j: ArenaIndex) -> Option<Type> {
+        if self.get_primitive_types().contains(&j) {
+            let primitive = self.arena.get(j).cloned()?;
+            return self
+                .get_mut(i)
+                .map(|r#type| mem::replace(r#type, primitive));
+        }
         self.dsu.union(
             *self.imap.get_rev(&i).unwrap(),
             *self.imap.get_rev(&j).unwrap(),
@@ -236,9 +400,14 @@ impl<'a> ITypeArena for TypeArenaWithDSU<'a> {
     }
 
     #[inline(always)]
-    fn get_primitive_types(&self) -> &[ArenaIndex; 9] {
+    fn get_primitive_types(&self) -> &[ArenaIndex; Primitive::ALL.len()] {
         self.arena.get_primitive_types()
     }
+
+    #[inline(always)]
+    fn resolve(&self, i: ArenaIndex) -> ArenaIndex {
+        self.find_representative(i).unwrap_or(i)
+    }
 }
 
 impl<'a> Drop for TypeArenaWithDSU<'a> {
@@ -0,0 +1,159 @@
+//! Override names of types by the JSON paths at which they are found
+use indexmap::{IndexMap, IndexSet};
+
+use crate::error::{Error, Result};
+use crate::schema::{ArenaIndex, ITypeArena, Schema, Type};
+
+/// A pass that renames named types (i.e. `Map`s, `Union`s and `Enum`s) as configured
+///
+/// It is expected to be applied after optimization, so that it is the merged types that are
+/// renamed. Types without any name hints are left unnamed.
+#[derive(Debug, Default)]
+pub struct Renamer {
+    /// Names of types keyed by JSON paths to them, e.g. `$.components[*]` for `Component`
+    ///
+    /// A path starts with `$` for the root, followed by any of `.key` or `["key"]` for a field of
+    /// a map, `[*]` for elements of an array or a tuple, `[0]` for a specific element of a tuple
+    /// and `.*` for values of a dict or all fields of a map. Unions are looked through on the way.
+    /// If a path leads to a union with only one map or enum in it (e.g. `Optional[Component]`),
+    /// the map or enum is renamed along with the union.
+    pub overrides: IndexMap<String, String>,
+    /// The prefix of every name, including those overridden
+    pub prefix: String,
+    /// The suffix of every name, including those overridden
+    pub suffix: String,
+}
+
+impl Renamer {
+    pub fn rename(&self, schema: &mut Schema) -> Result<()> {
+        for (path, name) in self.overrides.iter() {
+            let segments = parse_path(path).ok_or_else(|| {
+                Error::InvalidNaming(format!("`{}` is not a valid JSON path", path))
+            })?;
+            let mut renamed = false;
+            for arni in find(schema, &segments) {
+                let r#type = schema.arena.get(arni).unwrap();
+                let mut arnis = vec![arni];
+                if let Some(union) = r#type.as_union() {
+                    let named: Vec<ArenaIndex> = union
+                        .types
+                        .iter()
+                        .cloned()
+                        .filter(|&arni| {
+                            let r#type = schema.arena.get(arni).unwrap();
+                            r#type.is_map() || r#type.is_enum()
+                        })
+                        .collect();
+                    if let [member] = named[..] {
+                        arnis.push(member);
+                    }
+                }
+                for arni in arnis {
+                    if let Some(name_hints) = schema.arena.get_mut(arni).unwrap().name_hints_mut() {
+                        name_hints.clear();
+                        name_hints.insert(name.to_owned());
+                        renamed = true;
+                    }
+                }
+            }
+            if !renamed {
+                return Err(Error::InvalidNaming(format!(
+                    "`{}` leads to no type to be named",
+                    path
+                )));
+            }
+        }
+
+        if !self.prefix.is_empty() || !self.suffix.is_empty() {
+            let arnis: Vec<ArenaIndex> = schema.iter_topdown().collect();
+            for arni in arnis {
+                let name_hints = match schema.arena.get_mut(arni).unwrap().name_hints_mut() {
+                    Some(name_hints) if !name_hints.is_empty() => name_hints,
+                    _ => continue,
+                };
+                // Hints are joined first, so that the prefix and the suffix appear only once.
+                let name = format!("{}{}{}", self.prefix, name_hints, self.suffix);
+                name_hints.clear();
+                name_hints.insert(name);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A step in a JSON path
+#[derive(Debug)]
+enum Segment {
+    Key(String),
+    Index(usize),
+    Wildcard,
+}
+
+/// Parse a JSON path into segments, or `None` if it is invalid
+fn parse_path(path: &str) -> Option<Vec<Segment>> {
+    let mut rest = path.strip_prefix('$')?;
+    let mut segments = vec![];
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix(".*") {
+            segments.push(Segment::Wildcard);
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix('.') {
+            let end = after.find(['.', '[']).unwrap_or(after.len());
+            if end == 0 {
+                return None;
+            }
+            segments.push(Segment::Key(after[..end].to_owned()));
+            rest = &after[end..];
+        } else if let Some(after) = rest.strip_prefix('[') {
+            match after.chars().next() {
+                Some(quote @ ('"' | '\'')) => {
+                    let (key, after) = after[1..].split_once(quote)?;
+                    segments.push(Segment::Key(key.to_owned()));
+                    rest = after.strip_prefix(']')?;
+                }
+                _ => {
+                    let (inner, after) = after.split_once(']')?;
+                    segments.push(match inner {
+                        "*" => Segment::Wildcard,
+                        _ => Segment::Index(inner.parse().ok()?),
+                    });
+                    rest = after;
+                }
+            }
+        } else {
+            return None;
+        }
+    }
+    Some(segments)
+}
+
+/// Find all types that a JSON path leads to from the root
+fn find(schema: &Schema, segments: &[Segment]) -> IndexSet<ArenaIndex> {
+    let arena = &schema.arena;
+    let look_through = |arni: ArenaIndex| match *arena.get(arni).unwrap() {
+        Type::Union(ref union) => union.types.iter().cloned().collect(),
+        _ => vec![arni],
+    };
+    let mut current = IndexSet::new();
+    current.insert(schema.root);
+    for segment in segments {
+        current = current
+            .into_iter()
+            .flat_map(look_through)
+            .flat_map(|arni| match (arena.get(arni).unwrap(), segment) {
+                (Type::Map(map), Segment::Key(key)) => {
+                    map.fields.get(key).cloned().into_iter().collect()
+                }
+                (Type::Map(map), Segment::Wildcard) => map.fields.values().cloned().collect(),
+                (&Type::Dict(_, value), Segment::Wildcard) => vec![value],
+                (&Type::Array(inner), Segment::Wildcard | Segment::Index(_)) => vec![inner],
+                (Type::Tuple(types), Segment::Wildcard) => types.clone(),
+                (Type::Tuple(types), &Segment::Index(i)) => {
+                    types.get(i).cloned().into_iter().collect()
+                }
+                _ => vec![],
+            })
+            .collect();
+    }
+    current
+}
@@ -0,0 +1,226 @@
+//! Infer a schema from JSON as it is read, without materializing the whole document
+use indexmap::IndexMap;
+use inflector::Inflector;
+use serde::de::{self, DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde_json::de::IoRead;
+
+use std::{fmt, io, mem};
+
+use super::json::{InferrerClosure, JSONInferrer};
+use crate::error::{Error, Result};
+use crate::schema::{ArenaIndex, Primitive, Schema, Type};
+
+/// The number of types buffered before they are unioned, i.e. elements of an array or samples
+///
+/// Arrays no longer than it are inferred just as they are by [`JSONInferrer::infer`], including
+/// tuple detection. Longer ones are unioned chunk by chunk, so that the memory usage is bounded.
+/// Being longer than [`MAX_TUPLE_LEN`](super::json::MAX_TUPLE_LEN), they are never tuples anyway.
+const CHUNK_LEN: usize = 1024;
+
+/// An inferrer that infers a single `Schema` from samples read one after another, as configured
+///
+/// Types are built into the arena as tokens arrive, instead of from a parsed `JSONValue`. Just as
+/// in [`JSONInferrer::infer_many`], the root types of samples are unioned.
+pub struct StreamingInferrer<'a> {
+    closure: InferrerClosure<'a>,
+    root_name: Option<String>,
+    /// The union of all samples but those buffered, if any
+    unioned: Option<ArenaIndex>,
+    samples: Vec<ArenaIndex>,
+    /// The error that aborts deserialization, which is otherwise lost in a `serde_json::Error`
+    error: Option<Error>,
+}
+
+impl JSONInferrer {
+    /// Start streaming inference of samples whose root types are named after `root_name`
+    pub fn streaming(&self, root_name: Option<String>) -> StreamingInferrer<'_> {
+        StreamingInferrer {
+            closure: InferrerClosure::new(self),
+            root_name,
+            unioned: None,
+            samples: vec![],
+            error: None,
+        }
+    }
+
+    /// Infer a `Schema` from a single JSON value read from `reader`, without materializing it
+    ///
+    /// The `reader` is expected to be buffered, e.g. by [`io::BufReader`].
+    pub fn infer_reader(&self, reader: impl io::Read, root_name: Option<String>) -> Result<Schema> {
+        let mut streaming = self.streaming(root_name);
+        streaming.read(reader)?;
+        streaming.finish()
+    }
+}
+
+impl<'a> StreamingInferrer<'a> {
+    /// Read a sample from `reader` which has exactly one JSON value
+    pub fn read(&mut self, reader: impl io::Read) -> Result<()> {
+        let mut deserializer = serde_json::Deserializer::from_reader(reader);
+        self.read_one(&mut deserializer)?;
+        deserializer.end()?;
+        Ok(())
+    }
+
+    /// Read samples from `reader` which has zero or more JSON values separated by whitespaces
+    /// (e.g. [NDJSON](https://github.com/ndjson/ndjson-spec))
+    pub fn read_many(&mut self, reader: impl io::Read) -> Result<()> {
+        let mut deserializer = serde_json::Deserializer::from_reader(reader);
+        loop {
+            // Only whitespaces are consumed when checking if there is any more value.
+            match deserializer.end() {
+                Ok(()) => return Ok(()),
+                Err(e) if e.is_io() => return Err(e.into()),
+                Err(_) => self.read_one(&mut deserializer)?,
+            }
+        }
+    }
+
+    fn read_one<R: io::Read>(
+        &mut self,
+        deserializer: &mut serde_json::Deserializer<IoRead<R>>,
+    ) -> Result<()> {
+        let outer_name = self.root_name.clone();
+        let sample = ValueSeed {
+            inferrer: self,
+            outer_name,
+        }
+        .deserialize(deserializer)
+        .map_err(|e| self.error.take().unwrap_or_else(|| e.into()))?;
+        self.samples.push(sample);
+        if self.samples.len() == CHUNK_LEN {
+            let samples = mem::take(&mut self.samples);
+            self.unioned = Some(self.union_buffered(self.unioned, samples)?);
+        }
+        Ok(())
+    }
+
+    /// Union all samples read so far into a `Schema`, or fail if none has been read
+    pub fn finish(mut self) -> Result<Schema> {
+        let samples = mem::take(&mut self.samples);
+        if self.unioned.is_none() && samples.is_empty() {
+            return Err(Error::NoSamples);
+        }
+        let root = self.union_buffered(self.unioned, samples)?;
+        Ok(self.closure.finish(root))
+    }
+
+    /// Union buffered types, after the union of those before them if any
+    fn union_buffered(
+        &mut self,
+        unioned: Option<ArenaIndex>,
+        buffered: Vec<ArenaIndex>,
+    ) -> Result<ArenaIndex> {
+        let types = unioned.into_iter().chain(buffered).collect();
+        self.closure.union(types)
+    }
+
+    /// Keep an error to abort deserialization with
+    fn abort<T, E: de::Error>(&mut self, result: Result<T>) -> std::result::Result<T, E> {
+        result.map_err(|e| {
+            let message = e.to_string();
+            self.error = Some(e);
+            E::custom(message)
+        })
+    }
+}
+
+/// A seed to deserialize a JSON value into its type in the arena
+struct ValueSeed<'s, 'a> {
+    inferrer: &'s mut StreamingInferrer<'a>,
+    outer_name: Option<String>,
+}
+
+impl<'de, 's, 'a> DeserializeSeed<'de> for ValueSeed<'s, 'a> {
+    type Value = ArenaIndex;
+
+    fn deserialize<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> std::result::Result<Self::Value, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de, 's, 'a> Visitor<'de> for ValueSeed<'s, 'a> {
+    type Value = ArenaIndex;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "any JSON value")
+    }
+
+    fn visit_bool<E: de::Error>(self, _value: bool) -> std::result::Result<Self::Value, E> {
+        Ok(self.inferrer.closure.primitive(Primitive::Bool))
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> std::result::Result<Self::Value, E> {
+        Ok(self.inferrer.closure.infer_number(value as f64, true))
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> std::result::Result<Self::Value, E> {
+        Ok(self.inferrer.closure.infer_number(value as f64, true))
+    }
+
+    fn visit_f64<E: de::Error>(self, value: f64) -> std::result::Result<Self::Value, E> {
+        Ok(self.inferrer.closure.infer_number(value, false))
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> std::result::Result<Self::Value, E> {
+        Ok(self.inferrer.closure.infer_string(value, self.outer_name))
+    }
+
+    fn visit_unit<E: de::Error>(self) -> std::result::Result<Self::Value, E> {
+        Ok(self.inferrer.closure.primitive(Primitive::Null))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(
+        self,
+        mut seq: A,
+    ) -> std::result::Result<Self::Value, A::Error> {
+        let ValueSeed {
+            inferrer,
+            outer_name,
+        } = self;
+        // Elements are named as in `JSONInferrer::infer`.
+        let inner_name = outer_name.map(|outer_name| outer_name.to_singular());
+        let mut unioned = None;
+        let mut types = vec![];
+        while let Some(r#type) = seq.next_element_seed(ValueSeed {
+            inferrer: &mut *inferrer,
+            outer_name: inner_name.clone(),
+        })? {
+            types.push(r#type);
+            if types.len() == CHUNK_LEN {
+                let result = inferrer.union_buffered(unioned, mem::take(&mut types));
+                unioned = Some(inferrer.abort(result)?);
+            }
+        }
+        let result = match unioned {
+            None => inferrer.closure.infer_array(types),
+            Some(unioned) => inferrer
+                .union_buffered(Some(unioned), types)
+                .map(|inner| inferrer.closure.insert(Type::Array(inner))),
+        };
+        inferrer.abort(result)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(
+        self,
+        mut map: A,
+    ) -> std::result::Result<Self::Value, A::Error> {
+        let ValueSeed {
+            inferrer,
+            outer_name,
+        } = self;
+        let mut fields = IndexMap::new();
+        while let Some(key) = map.next_key::<String>()? {
+            let r#type = map.next_value_seed(ValueSeed {
+                inferrer: &mut *inferrer,
+                outer_name: Some(key.to_pascal_case()),
+            })?;
+            fields.insert(key, r#type);
+        }
+        let result = inferrer.closure.infer_object(fields, outer_name);
+        inferrer.abort(result)
+    }
+}
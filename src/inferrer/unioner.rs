@@ -1,60 +1,253 @@
-use indexmap::IndexMap;
+use indexmap::{IndexMap, IndexSet};
+use inflector::Inflector;
+use itertools::Itertools;
 // /// Infer a schema from a given JSONValue
 // use serde_json::Value as JSONValue;
 
-use std::{collections::HashSet, mem};
+use std::{
+    collections::{BTreeSet, HashSet},
+    mem,
+};
 
-use crate::schema::{ArenaIndex, ITypeArena, Map, NameHints, Type, Union};
+use super::json::TaggedUnionDetection;
+use crate::error::{Error, Result};
+use crate::schema::{ArenaIndex, Enum, ITypeArena, Map, NameHints, Number, Primitive, Type, Union};
 
 /// Union a sequence of `types` into a single [`Type`] in the given `arena`
 pub fn union(
     arena: &mut impl ITypeArena,
     types: impl IntoIterator<Item = ArenaIndex>,
-) -> ArenaIndex {
+) -> Result<ArenaIndex> {
     UnionerClosure::new(arena).union(types)
 }
 
+/// The coarse kind of a [`Type`], used to determine whether types are alike
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Kind {
+    Number,
+    String,
+    Bool,
+    Map,
+    Array,
+}
+
+impl Kind {
+    /// Get the kind of a type, or `None` if it goes with any kind (e.g. `Null`)
+    pub fn of(r#type: &Type) -> Option<Kind> {
+        match *r#type {
+            Type::Int | Type::Float | Type::Number(_) => Some(Kind::Number),
+            Type::String
+            | Type::Enum(_)
+            | Type::Date
+            | Type::UUID
+            | Type::PlainDate
+            | Type::Time
+            | Type::Duration
+            | Type::URL
+            | Type::Email
+            | Type::IPAddress
+            | Type::Decimal
+            | Type::Bytes => Some(Kind::String),
+            Type::Bool => Some(Kind::Bool),
+            Type::Map(_) | Type::Dict(_, _) => Some(Kind::Map),
+            Type::Array(_) | Type::Tuple(_) => Some(Kind::Array),
+            Type::Union(_) | Type::Null | Type::Missing | Type::Any => None,
+        }
+    }
+}
+
+/// Check if there are types of at least two different [`Kind`]s in `types`
+pub fn are_heterogeneous(arena: &impl ITypeArena, types: &[ArenaIndex]) -> bool {
+    types
+        .iter()
+        .filter_map(|&r#type| arena.get(r#type).and_then(Kind::of))
+        .collect::<HashSet<_>>()
+        .len()
+        > 1
+}
+
+/// Keys that are conventionally used for tags of tagged unions
+const TAG_KEYS: &[&str] = &[
+    "type",
+    "kind",
+    "tag",
+    "event",
+    "object",
+    "@type",
+    "$type",
+    "__typename",
+];
+
 /// A unioner with a reference to some a arena associated
 pub struct UnionerClosure<'a, T: ITypeArena> {
     arena: &'a mut T,
+    tagged_union_detection: Option<&'a TaggedUnionDetection>,
+    max_enum_variants: Option<usize>,
 }
 
 impl<'a, T: ITypeArena> UnionerClosure<'a, T> {
     pub fn new(arena: &'a mut T) -> Self {
-        Self { arena }
+        Self {
+            arena,
+            tagged_union_detection: None,
+            max_enum_variants: None,
+        }
     }
 
-    pub fn union(mut self, types: impl IntoIterator<Item = ArenaIndex>) -> ArenaIndex {
+    /// Keep maps tagged by different values of a field apart instead of merging them, if set
+    pub fn detecting_tagged_unions(
+        mut self,
+        tagged_union_detection: Option<&'a TaggedUnionDetection>,
+    ) -> Self {
+        self.tagged_union_detection = tagged_union_detection;
+        self
+    }
+
+    /// Collapse enums into `String` as soon as they have more than `max_variants` variants, if set
+    ///
+    /// Strings are enum candidates before finalized. Those that can no longer be accepted are not
+    /// kept, so that e.g. streaming unique IDs does not collect all of them. A tag is never
+    /// collapsed, as it is the only variant of an enum, with `max_variants` at least 1.
+    pub fn collapsing_enums(mut self, max_variants: Option<usize>) -> Self {
+        self.max_enum_variants = max_variants;
+        self
+    }
+
+    /// Find a field of strings that tells apart maps of different structures, and group the maps
+    /// by its values
+    ///
+    /// As strings are enum candidates of a single variant before finalized, a tag is the only
+    /// variant of the enum of the field in a map.
+    fn group_by_tag(&self, maps: &[ArenaIndex]) -> Option<IndexMap<String, Vec<ArenaIndex>>> {
+        let tagged_union_detection = self.tagged_union_detection?;
+        let maps: Vec<(ArenaIndex, &Map)> = maps
+            .iter()
+            .map(|&arni| (arni, self.arena.get(arni).unwrap().as_map().unwrap()))
+            .collect();
+        let (_, first) = maps.first()?;
+        first.fields.keys().find_map(|key| {
+            let mut groups: IndexMap<&str, Vec<(ArenaIndex, &Map)>> = IndexMap::new();
+            let mut occurrences = 0;
+            for &(arni, map) in maps.iter() {
+                let (tag, count) = map.tag(key, &*self.arena)?;
+                occurrences += count;
+                groups.entry(tag).or_default().push((arni, map));
+            }
+            if groups.len() < 2 || groups.len() > tagged_union_detection.max_tags {
+                return None;
+            }
+            // Unless named as a tag conventionally, some tag is expected to be seen more than
+            // once, so that e.g. names or IDs are not mistaken for tags. Occurrences are counted
+            // instead of maps, as maps might have been unioned already.
+            if occurrences == groups.len() && !TAG_KEYS.contains(&key.as_str()) {
+                return None;
+            }
+            // Otherwise, it is just an ordinary field, such as `"status": "active"`.
+            let are_structures_distinct = groups
+                .values()
+                .map(|maps| {
+                    maps.iter()
+                        .flat_map(|(_, map)| map.fields.keys())
+                        .collect::<BTreeSet<_>>()
+                })
+                .all_unique();
+            are_structures_distinct.then(|| {
+                groups
+                    .into_iter()
+                    .map(|(tag, maps)| {
+                        (
+                            tag.to_owned(),
+                            maps.into_iter().map(|(arni, _)| arni).collect(),
+                        )
+                    })
+                    .collect()
+            })
+        })
+    }
+
+    pub fn union(mut self, types: impl IntoIterator<Item = ArenaIndex>) -> Result<ArenaIndex> {
         self.runion(types)
     }
 
-    pub fn runion(&mut self, types: impl IntoIterator<Item = ArenaIndex>) -> ArenaIndex {
-        let mut unioned = HashSet::new();
+    /// Give up the slot kept for the first type of a kind (e.g. the first enum) to be unioned
+    /// into, as the types of the kind turn out to be `favored` instead (e.g. `String`)
+    ///
+    /// The slot is removed in favor of `favored`, instead of being left empty in the arena.
+    fn give_up_slot(&mut self, slot: ArenaIndex, favored: ArenaIndex) {
+        self.arena.remove_in_favor_of(slot, favored);
+    }
+
+    pub fn runion(&mut self, types: impl IntoIterator<Item = ArenaIndex>) -> Result<ArenaIndex> {
+        self.do_runion(types, true)
+    }
+
+    /// Union types, with maps grouped by tags only if `to_group_by_tag`
+    ///
+    /// Maps of a group are unioned without grouping them again, so that they always end up in one
+    /// map even if another field tells them apart. Their fields are still unioned with grouping.
+    fn do_runion(
+        &mut self,
+        types: impl IntoIterator<Item = ArenaIndex>,
+        to_group_by_tag: bool,
+    ) -> Result<ArenaIndex> {
+        // Ordered, so that the output is deterministic
+        let mut unioned = IndexSet::new();
         // The first Type::Map is kept to be unioned into.
         let mut first_map: Option<ArenaIndex> = None;
         // All Maps are collected at first and then merged into one unioned Map, field by field.
         let mut maps: Option<IndexMap<String, Vec<ArenaIndex>>> = None;
         let mut map_count = 0; // Used to determine whether a field is present in all Maps.
         let mut map_name_hints = NameHints::new();
+        // Maps are only collected in the first pass, as they might be variants of a tagged union.
+        let mut map_types = vec![];
+        // The first Type::Enum is kept to be unioned into, with variants of other Enums merged.
+        let mut first_enum: Option<ArenaIndex> = None;
+        let mut variants: IndexMap<String, usize> = IndexMap::new();
+        let mut enum_name_hints = NameHints::new();
+        // The first Type::Number is kept to be unioned into, with ranges of others merged.
+        let mut first_number: Option<ArenaIndex> = None;
+        let mut number: Option<Number> = None;
+        // The first Type::Dict is kept to be unioned into. Its keys and values are unioned
+        // respectively.
+        let mut first_dict: Option<ArenaIndex> = None;
+        let mut dict_keys = vec![];
+        let mut dict_values = vec![];
         let mut first_union: Option<ArenaIndex> = None;
         let mut union_name_hints = NameHints::new();
         // All Arrays are collected at first. Then their inner types are unioned recursively.
         // e.g. `int[], (int | bool)[], string[]` -> (int | bool | string)[]
         let mut arrays = vec![];
+        // All Tuples are collected at first. If they are of the same length and alike at every
+        // position, their inner types are unioned position by position. O.W., they are treated as
+        // Arrays.
+        // e.g. `[int, str], [float, str]` -> [float, str]; `[int, str], [int]` -> (int | str)[]
+        let mut tuples: Vec<Vec<ArenaIndex>> = vec![];
         // TODO: keep first_array?
 
+        let types: Vec<ArenaIndex> = types
+            .into_iter()
+            .map(|r#type| self.arena.resolve(r#type))
+            .unique()
+            .collect();
+        if let Some(r#type) = types
+            .iter()
+            .find(|&&r#type| self.arena.get(r#type).is_none())
+        {
+            return Err(Error::MalformedSchema(format!(
+                "{:?} is absent from the arena during unioning",
+                r#type
+            )));
+        }
         // Expand any nested unions. Due to borrow issues, collecting is inevitable
         let types: Vec<ArenaIndex> = types
             .into_iter()
             .flat_map(|r#type| {
                 // dbg!(r#type);
-                match self
-                    .arena
-                    .get(r#type)
-                    .expect("The type should be present in the arena during unioning")
-                {
+                match self.arena.get(r#type).unwrap() {
                     Type::Union(_) => {
-                        let Union { name_hints, types } = if let Some(first_union) = first_union {
+                        let Union {
+                            name_hints, types, ..
+                        } = if let Some(first_union) = first_union {
                             self.arena
                                 .remove_in_favor_of(r#type, first_union)
                                 .unwrap()
@@ -72,44 +265,79 @@ impl<'a, T: ITypeArena> UnionerClosure<'a, T> {
                     _ => vec![r#type], // TODO: avoid unnecessary Vec
                 }
             })
-            .collect::<HashSet<_>>()
+            .collect();
+        // Deduplicate while keeping the order, so that e.g. enum variants are in the order they
+        // are first seen. Types removed in favor of others are the same as those.
+        let types: Vec<ArenaIndex> = types
             .into_iter()
+            .map(|r#type| self.arena.resolve(r#type))
+            .unique()
             .collect();
         for r#type in types {
             // dbg!(r#type, self.arena.get(r#type));
             match *self.arena.get(r#type).unwrap() {
-                Type::Map(_) => {
-                    let map;
-                    if let Some(first_map) = first_map {
-                        // If it is not the first map in the union, just remove the type from the
-                        // arena.
-                        map = self
-                            .arena
-                            .remove_in_favor_of(r#type, first_map)
+                Type::Map(_) => map_types.push(r#type),
+                Type::Enum(_) => {
+                    // Similar to Maps, the first enum is kept in its slot.
+                    let r#enum = if let Some(first_enum) = first_enum {
+                        self.arena
+                            .remove_in_favor_of(r#type, first_enum)
+                            .unwrap()
+                            .into_enum()
                             .unwrap()
-                            .into_map()
-                            .unwrap();
                     } else {
-                        // O.W., just take the inner of the first map out so that its slot can be
-                        // reused again with ArenaIndex left intact.
-                        first_map = Some(r#type);
-                        map = mem::take(self.arena.get_mut(r#type).unwrap())
-                            .into_map()
-                            .unwrap();
-                    }
-                    let maps = maps.get_or_insert_with(Default::default);
-                    for (key, r#type) in map.fields.into_iter() {
-                        maps.entry(key).or_default().push(r#type);
+                        first_enum = Some(r#type);
+                        mem::take(self.arena.get_mut(r#type).unwrap())
+                            .into_enum()
+                            .unwrap()
+                    };
+                    for (value, count) in r#enum.variants.into_iter() {
+                        *variants.entry(value).or_default() += count;
                     }
-                    map_count += 1;
-                    // NOTE: For in-place HashSet union, `.extend` is needed instead of `.union`.
-                    map_name_hints.extend(map.name_hints.into_inner());
+                    enum_name_hints.extend(r#enum.name_hints.into_inner());
+                }
+                Type::Number(_) => {
+                    // Similar to Enums, the first number is kept in its slot. It is put into the
+                    // union right away, so that the order is kept as with other primitive types.
+                    let other = if let Some(first_number) = first_number {
+                        self.arena
+                            .remove_in_favor_of(r#type, first_number)
+                            .unwrap()
+                            .into_number()
+                            .unwrap()
+                    } else {
+                        first_number = Some(r#type);
+                        unioned.insert(r#type);
+                        *self.arena.get(r#type).unwrap().as_number().unwrap()
+                    };
+                    number = Some(number.map_or(other, |number| number.union(&other)));
                 }
                 Type::Array(_) => {
                     // TODO: FIX : in favor of?
                     let inner = self.arena.remove(r#type).unwrap().into_array().unwrap();
                     arrays.push(inner);
                 }
+                Type::Dict(_, _) => {
+                    // Similar to Maps, the first dict is kept in its slot.
+                    let (key, value) = if let Some(first_dict) = first_dict {
+                        self.arena
+                            .remove_in_favor_of(r#type, first_dict)
+                            .unwrap()
+                            .into_dict()
+                            .unwrap()
+                    } else {
+                        first_dict = Some(r#type);
+                        mem::take(self.arena.get_mut(r#type).unwrap())
+                            .into_dict()
+                            .unwrap()
+                    };
+                    dict_keys.push(key);
+                    dict_values.push(value);
+                }
+                Type::Tuple(_) => {
+                    let types = self.arena.remove(r#type).unwrap().into_tuple().unwrap();
+                    tuples.push(types);
+                }
                 Type::Union(_) => unreachable!(), // union should have been expanded above
                 _ => {
                     // O.W. it is a primitive type. Then just add it to the union as is.
@@ -121,26 +349,101 @@ impl<'a, T: ITypeArena> UnionerClosure<'a, T> {
                     // See the linked-list or tree-recursion test case.
                     unioned.insert(r#type);
                 } // Type::Int => {
-                                                   //     unioned.insert(self.arena.get_index_of_primitive(Type::Int));
+                                                   //     unioned.insert(self.arena.get_index_of_primitive(Primitive::Int));
                                                    // }
                                                    // Type::Float => {
-                                                   //     unioned.insert(self.arena.get_index_of_primitive(Type::Float));
+                                                   //     unioned.insert(self.arena.get_index_of_primitive(Primitive::Float));
                                                    // }
                                                    // Type::Bool => {
-                                                   //     unioned.insert(self.arena.get_index_of_primitive(Type::Bool));
+                                                   //     unioned.insert(self.arena.get_index_of_primitive(Primitive::Bool));
                                                    // }
                                                    // Type::String => {
-                                                   //     unioned.insert(self.arena.get_index_of_primitive(Type::String));
+                                                   //     unioned.insert(self.arena.get_index_of_primitive(Primitive::String));
                                                    // }
                                                    // Type::Null => {
-                                                   //     unioned.insert(self.arena.get_index_of_primitive(Type::Null));
+                                                   //     unioned.insert(self.arena.get_index_of_primitive(Primitive::Null));
                                                    // }
                                                    // Type::Any => {
-                                                   //     unioned.insert(self.arena.get_index_of_primitive(Type::Any));
+                                                   //     unioned.insert(self.arena.get_index_of_primitive(Primitive::Any));
                                                    // }
             }
         }
 
+        if first_dict.is_none() && to_group_by_tag {
+            if let Some(groups) = self.group_by_tag(&map_types) {
+                // Maps with the same tag are unioned into a variant of the tagged union, named
+                // after the tag.
+                for (tag, types) in groups {
+                    let variant = self.do_runion(types, false)?;
+                    let prefix = tag.to_pascal_case();
+                    let map = self.arena.get_mut(variant).unwrap().as_map_mut().unwrap();
+                    let name_hints = mem::take(&mut map.name_hints);
+                    // The tagged union is named after the maps as well, without tags prefixed.
+                    union_name_hints.extend(name_hints.iter().map(|name| {
+                        match name.strip_prefix(&prefix) {
+                            Some(name) if !name.is_empty() => name.to_owned(),
+                            _ => name.to_owned(),
+                        }
+                    }));
+                    map.name_hints
+                        .extend(name_hints.into_inner().into_iter().map(|name| {
+                            if name.starts_with(&prefix) {
+                                name
+                            } else {
+                                format!("{}{}", prefix, name)
+                            }
+                        }));
+                    if map.name_hints.is_empty() {
+                        map.name_hints.insert(prefix);
+                    }
+                    unioned.insert(variant);
+                }
+                map_types.clear();
+            }
+        }
+        for r#type in map_types {
+            let map;
+            if let Some(first_map) = first_map {
+                // If it is not the first map in the union, just remove the type from the
+                // arena.
+                map = self
+                    .arena
+                    .remove_in_favor_of(r#type, first_map)
+                    .unwrap()
+                    .into_map()
+                    .unwrap();
+            } else {
+                // O.W., just take the inner of the first map out so that its slot can be
+                // reused again with ArenaIndex left intact.
+                first_map = Some(r#type);
+                map = mem::take(self.arena.get_mut(r#type).unwrap())
+                    .into_map()
+                    .unwrap();
+            }
+            let maps = maps.get_or_insert_with(Default::default);
+            for (key, r#type) in map.fields.into_iter() {
+                maps.entry(key).or_default().push(r#type);
+            }
+            map_count += 1;
+            // NOTE: For in-place IndexSet union, `.extend` is needed instead of `.union`.
+            map_name_hints.extend(map.name_hints.into_inner());
+        }
+
+        if let Some(slot) = first_dict {
+            if let Some(maps) = maps.take() {
+                // A Map at the same position of a Dict is just a Dict whose keys happen not to
+                // look like IDs. So its fields are folded into the Dict.
+                self.give_up_slot(first_map.unwrap(), slot);
+                if !maps.is_empty() {
+                    dict_keys.push(self.arena.get_index_of_primitive(Primitive::String));
+                }
+                dict_values.extend(maps.into_iter().flat_map(|(_, types)| types));
+            }
+            let key = self.runion(dict_keys)?;
+            let value = self.runion(dict_values)?;
+            *self.arena.get_mut(slot).unwrap() = Type::Dict(key, value);
+            unioned.insert(slot);
+        }
         // dbg!(&first_map, &maps);
         if let Some(maps) = maps {
             // merge maps recursively by unioning every possible fields
@@ -149,64 +452,125 @@ impl<'a, T: ITypeArena> UnionerClosure<'a, T> {
                 .map(|(key, mut types)| {
                     // The field is not required if not present in every Map.
                     if types.len() < map_count {
-                        types.push(self.arena.get_index_of_primitive(Type::Missing));
+                        types.push(self.arena.get_index_of_primitive(Primitive::Missing));
                         // TypeScript: ?/undefined; Python: Missing/NotRequired
                     }
-                    (key, self.runion(types))
+                    Ok((key, self.runion(types)?))
                 })
-                .collect();
+                .collect::<Result<_>>()?;
             // dbg!(&unioned_map);
             if unioned_map.is_empty() {
                 // every map is empty (no field at all)
                 // TODO: Any or unit type?
                 // TODO: should slot be removed from arena here?
-                unioned.insert(self.arena.get_index_of_primitive(Type::Any)); // Any
+                unioned.insert(self.arena.get_index_of_primitive(Primitive::Any));
+            // Any
             } else {
                 let slot = first_map.unwrap();
                 *self.arena.get_mut(slot).unwrap() = Type::Map(Map {
                     name_hints: map_name_hints,
+                    name: None,
                     fields: unioned_map,
                 });
                 unioned.insert(slot);
             }
         }
+        if !tuples.is_empty() {
+            let len = tuples[0].len();
+            let is_consistent = arrays.is_empty()
+                && tuples.iter().all(|types| types.len() == len)
+                && (0..len).all(|i| {
+                    let types: Vec<ArenaIndex> = tuples.iter().map(|types| types[i]).collect();
+                    !are_heterogeneous(self.arena, &types)
+                });
+            if is_consistent {
+                let types = (0..len)
+                    .map(|i| {
+                        let types: Vec<ArenaIndex> = tuples.iter().map(|types| types[i]).collect();
+                        self.runion(types)
+                    })
+                    .collect::<Result<_>>()?;
+                unioned.insert(self.arena.insert(Type::Tuple(types)));
+            } else {
+                arrays.extend(tuples.into_iter().flatten());
+            }
+        }
         if !arrays.is_empty() {
-            let inner = self.runion(arrays);
+            let inner = self.runion(arrays)?;
             unioned.insert(self.arena.insert(Type::Array(inner)));
         }
-        if unioned.contains(&self.arena.get_index_of_primitive(Type::Int))
-            && unioned.contains(&self.arena.get_index_of_primitive(Type::Float))
+        if let (Some(slot), Some(number)) = (first_number, number) {
+            let int = self.arena.get_index_of_primitive(Primitive::Int);
+            let float = self.arena.get_index_of_primitive(Primitive::Float);
+            if unioned.contains(&int) || unioned.contains(&float) {
+                // Ranges are unknown for numbers that are not tracked (e.g. from a JSON Schema).
+                let favored = if number.is_integer { int } else { float };
+                unioned.shift_remove(&slot);
+                self.give_up_slot(slot, favored);
+                unioned.insert(favored);
+            } else {
+                *self.arena.get_mut(slot).unwrap() = Type::Number(number);
+            }
+        }
+        if unioned.contains(&self.arena.get_index_of_primitive(Primitive::Int))
+            && unioned.contains(&self.arena.get_index_of_primitive(Primitive::Float))
         {
             // In JS(ON), int and float are both number, which implies 1.0 is serialized as 1.
             // So if both int and float present in the union, just treat it as float.
-            unioned.remove(&self.arena.get_index_of_primitive(Type::Int));
+            unioned.shift_remove(&self.arena.get_index_of_primitive(Primitive::Int));
         }
         {
-            // Mix of string-like types is treated as string
-            let uuid = unioned.contains(&self.arena.get_index_of_primitive(Type::UUID));
-            let datetime = unioned.contains(&self.arena.get_index_of_primitive(Type::Date));
-            let string = unioned.contains(&self.arena.get_index_of_primitive(Type::String));
-
-            if (uuid & datetime) | (string & (uuid ^ datetime)) {
-                // at least two are true
-                // https://stackoverflow.com/a/3090404/5488616
-                unioned.remove(&self.arena.get_index_of_primitive(Type::Date));
-                unioned.remove(&self.arena.get_index_of_primitive(Type::UUID));
-                unioned.insert(self.arena.get_index_of_primitive(Type::String));
+            // Mix of string-like types is treated as string, except that dates mixed with
+            // date-times are just date-times.
+            let formats: Vec<ArenaIndex> = Primitive::ALL
+                .iter()
+                .copied()
+                .filter(|&primitive| Type::from(primitive).is_formatted_string())
+                .map(|primitive| self.arena.get_index_of_primitive(primitive))
+                .filter(|arni| unioned.contains(arni))
+                .collect();
+            let string = unioned.contains(&self.arena.get_index_of_primitive(Primitive::String));
+            let r#enum = first_enum.is_some();
+            let is_enum_oversized = self
+                .max_enum_variants
+                .is_some_and(|max_variants| variants.len() > max_variants);
+            let datetime = self.arena.get_index_of_primitive(Primitive::Date);
+            let date = self.arena.get_index_of_primitive(Primitive::PlainDate);
+
+            if formats == [datetime, date] && !string && !r#enum {
+                unioned.shift_remove(&date);
+            } else if formats.len() + string as usize + r#enum as usize >= 2 || is_enum_oversized {
+                for arni in formats {
+                    unioned.shift_remove(&arni);
+                }
+                let the_string = self.arena.get_index_of_primitive(Primitive::String);
+                unioned.insert(the_string);
+                if let Some(slot) = first_enum.take() {
+                    self.give_up_slot(slot, the_string);
+                }
             }
         }
+        if let Some(slot) = first_enum {
+            *self.arena.get_mut(slot).unwrap() = Type::Enum(Enum {
+                name_hints: enum_name_hints,
+                name: None,
+                variants,
+            });
+            unioned.insert(slot);
+        }
 
         // dbg!(&tys, unioned.iter().collect::<Vec<_>>());
         // if first_union.is_some() {
         //     //
         //     assert!(unioned.len() > 1);
         // }
-        match unioned.len() {
-            0 => self.arena.get_index_of_primitive(Type::Any), // Any
-            1 => unioned.drain().next().unwrap(),
+        Ok(match unioned.len() {
+            0 => self.arena.get_index_of_primitive(Primitive::Any), // Any
+            1 => unioned.pop().unwrap(),
             _ => {
                 let union = Type::Union(Union {
                     name_hints: union_name_hints,
+                    name: None,
                     types: unioned,
                 });
                 if let Some(slot) = first_union {
@@ -216,6 +580,6 @@ impl<'a, T: ITypeArena> UnionerClosure<'a, T> {
                     self.arena.insert(union)
                 }
             }
-        }
+        })
     }
 }
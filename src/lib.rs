@@ -6,10 +6,13 @@ macro_rules! for_wasm {
 }
 // https://github.com/seanmonstar/reqwest/blob/29b15cb1d2ed59db3b57d6a5ff98236435efc9cd/src/lib.rs#L204
 
+mod error;
 pub mod inferrer;
 pub mod schema;
 pub mod target;
 
+pub use error::{Error, Result};
+
 for_wasm! {
     mod wasm;
 }
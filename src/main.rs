@@ -1,3 +1,421 @@
+use clap::{Parser, ValueEnum};
+use serde_json::Value;
+
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, Read, Write},
+    path::PathBuf,
+    process,
+};
+
+use json2pyi::inferrer::{
+    infer_from_json_schema, DictDetection, EnumDetection, JSONInferrer, MapSimilarity, Optimizer,
+    Renamer, TaggedUnionDetection,
+};
+use json2pyi::schema::{Schema, SchemaIR};
+use json2pyi::target::{
+    Indentation, JSONSchema, Order, PythonClass, PythonKind, PythonTypedDict, PythonVersion, Quote,
+    RustSerde, TargetGenerator, TypeScriptInterface,
+};
+use json2pyi::Error;
+
+/// Infer a type schema from a sample JSON and generate Python type definitions accordingly
+#[derive(Debug, Parser)]
+#[command(name = "json2pyi", version)]
+struct Args {
+    /// The sample JSON file(s) to read from (`-` for stdin)
+    ///
+    /// When more than one sample is given, a single schema is inferred from all of them.
+    #[arg(default_value = "-")]
+    inputs: Vec<PathBuf>,
+
+    /// Treat every input as newline-delimited JSON, with each line being a sample
+    #[arg(long)]
+    ndjson: bool,
+
+    /// Treat the input as a JSON Schema instead of a sample JSON
+    #[arg(long, conflicts_with = "ndjson")]
+    json_schema: bool,
+
+    /// Treat the input as a schema IR saved by `--target ir`, which is used as is without being
+    /// optimized again
+    #[arg(long, conflicts_with_all = ["ndjson", "json_schema"])]
+    ir: bool,
+
+    /// The file to write the generated code to (stdout if not specified)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// The kind of type definitions to generate
+    #[arg(short, long, value_enum, default_value_t = Target::Dataclass)]
+    target: Target,
+
+    /// The name of the root type
+    #[arg(short, long)]
+    root_name: Option<String>,
+
+    /// Name the type at a JSON path, e.g. `$.components[*]=Component` (can be repeated)
+    #[arg(long, value_name = "PATH=NAME")]
+    rename: Vec<String>,
+
+    /// The prefix of the names of all generated types
+    #[arg(long, value_name = "PREFIX", default_value = "")]
+    name_prefix: String,
+
+    /// The suffix of the names of all generated types
+    #[arg(long, value_name = "SUFFIX", default_value = "")]
+    name_suffix: String,
+
+    /// Do not generate type aliases for complex unions
+    #[arg(long)]
+    no_type_alias_for_union: bool,
+
+    /// Indent with N spaces in class bodies
+    #[arg(long, value_name = "N", default_value_t = 4)]
+    indent: usize,
+
+    /// Indent with tabs instead of spaces in class bodies
+    #[arg(long, conflicts_with = "indent")]
+    tab: bool,
+
+    /// Use branded string types for dates, UUIDs and other string formats in TypeScript interfaces
+    #[arg(long)]
+    brand_string_formats: bool,
+
+    /// Do not make generated Rust types and fields `pub`
+    #[arg(long)]
+    private: bool,
+
+    /// Use the narrowest Rust integer types (e.g. `u8`) that fit all values in the sample
+    #[arg(long)]
+    narrow_integers: bool,
+
+    /// The order of generated Python type definitions
+    ///
+    /// Defaults to `source` for classes and `dependencies-first` for TypedDicts.
+    #[arg(long, value_enum)]
+    order: Option<OrderArg>,
+
+    /// The minimum Python version for generated Python code to run on, which determines the syntax
+    #[arg(long, value_name = "X.Y", default_value_t = PythonVersion(3, 8))]
+    python_version: PythonVersion,
+
+    /// The quote used for strings in TypedDict definitions
+    #[arg(long, value_enum, default_value_t = QuoteArg::Double)]
+    quote: QuoteArg,
+
+    /// Detect strings with only a few distinct values as enums
+    #[arg(long)]
+    detect_enums: bool,
+
+    /// The maximum number of distinct values of an enum
+    #[arg(long, value_name = "N", default_value_t = EnumDetection::default().max_variants)]
+    enum_max_variants: usize,
+
+    /// The minimum number of occurrences of strings to be detected as an enum
+    #[arg(long, value_name = "N", default_value_t = EnumDetection::default().min_occurrences)]
+    enum_min_occurrences: usize,
+
+    /// The maximum ratio of distinct values to occurrences of strings to be detected as an enum
+    #[arg(long, value_name = "RATIO", default_value_t = EnumDetection::default().max_variant_ratio)]
+    enum_max_variant_ratio: f64,
+
+    /// Keep objects tagged by a field of a few distinct strings (e.g. `"type": "click"`) apart as
+    /// variants of a union, instead of merging them into one
+    #[arg(long)]
+    detect_tagged_unions: bool,
+
+    /// The maximum number of distinct tags of a tagged union
+    #[arg(long, value_name = "N", default_value_t = TaggedUnionDetection::default().max_tags)]
+    tagged_union_max_tags: usize,
+
+    /// Do not detect fixed-length arrays with elements of different kinds as tuples
+    #[arg(long)]
+    no_detect_tuples: bool,
+
+    /// Do not detect dictionary-style objects (e.g. keyed by IDs) as dicts
+    #[arg(long)]
+    no_detect_dicts: bool,
+
+    /// The minimum number of keys of an object with values all alike to be detected as a dict
+    #[arg(long, value_name = "N", default_value_t = DictDetection::default().min_keys)]
+    dict_min_keys: usize,
+
+    /// Do not detect formats of strings other than date-times and UUIDs (e.g. dates, URLs and
+    /// decimal numbers)
+    #[arg(long)]
+    no_detect_string_formats: bool,
+
+    /// Do not merge data types with similar structure
+    #[arg(long)]
+    no_merge_similar_datatypes: bool,
+
+    /// The similarity of field names and types above which data types are merged
+    #[arg(long, value_name = "RATIO", default_value_t = MapSimilarity::default().threshold)]
+    similarity_threshold: f64,
+
+    /// The weight of fields only in the former data type when measuring similarity
+    #[arg(long, value_name = "WEIGHT", default_value_t = MapSimilarity::default().alpha)]
+    similarity_alpha: f64,
+
+    /// The weight of fields only in the latter data type when measuring similarity
+    #[arg(long, value_name = "WEIGHT", default_value_t = MapSimilarity::default().beta)]
+    similarity_beta: f64,
+
+    /// Do not merge unions with the same inner types
+    #[arg(long)]
+    no_merge_same_unions: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Target {
+    Dataclass,
+    DataclassWithJson,
+    PydanticBaseModel,
+    PydanticDataclass,
+    PydanticV2BaseModel,
+    TypedDictClass,
+    TypedDictInline,
+    NestedTypedDict,
+    TypescriptInterface,
+    NestedTypescriptInterface,
+    RustSerde,
+    JsonSchema,
+    /// The schema itself, which can be edited by hand and read back with `--ir`
+    Ir,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OrderArg {
+    /// Define every type before it is referenced
+    DependenciesFirst,
+    /// Define the root type first, followed by the types it references level by level
+    RootFirst,
+    /// Sort types by name
+    Alphabetical,
+    /// Define types in the order they are first seen from the root
+    Source,
+}
+
+impl From<OrderArg> for Order {
+    fn from(order: OrderArg) -> Self {
+        match order {
+            OrderArg::DependenciesFirst => Order::DependenciesFirst,
+            OrderArg::RootFirst => Order::RootFirst,
+            OrderArg::Alphabetical => Order::Alphabetical,
+            OrderArg::Source => Order::Source,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum QuoteArg {
+    Single,
+    Double,
+}
+
+impl Args {
+    /// Get the generator for the target, or `None` for the schema IR which is serialized directly
+    fn generator(&self) -> Option<Box<dyn TargetGenerator>> {
+        let indentation = || {
+            if self.tab {
+                Indentation::Tab
+            } else {
+                Indentation::Space(self.indent)
+            }
+        };
+        let python_class = |kind| -> Box<dyn TargetGenerator> {
+            Box::new(PythonClass {
+                kind,
+                to_generate_type_alias_for_union: !self.no_type_alias_for_union,
+                indentation: indentation(),
+                order: self.order.map_or(Order::Source, Order::from),
+                python_version: self.python_version,
+            })
+        };
+        let python_typed_dict = |to_nest_when_possible| -> Box<dyn TargetGenerator> {
+            Box::new(PythonTypedDict {
+                quote_type: match self.quote {
+                    QuoteArg::Single => Quote::Single,
+                    QuoteArg::Double => Quote::Double,
+                },
+                to_generate_type_alias_for_union: !self.no_type_alias_for_union,
+                to_nest_when_possible,
+                order: self.order.map_or(Order::DependenciesFirst, Order::from),
+                python_version: self.python_version,
+            })
+        };
+        let typescript_interface = |to_nest_when_possible| -> Box<dyn TargetGenerator> {
+            Box::new(TypeScriptInterface {
+                indentation: indentation(),
+                to_generate_type_alias_for_union: !self.no_type_alias_for_union,
+                to_nest_when_possible,
+                to_brand_string_formats: self.brand_string_formats,
+            })
+        };
+        Some(match self.target {
+            Target::Dataclass => python_class(PythonKind::Dataclass),
+            Target::DataclassWithJson => python_class(PythonKind::DataclassWithJSON),
+            Target::PydanticBaseModel => python_class(PythonKind::PydanticBaseModel),
+            Target::PydanticDataclass => python_class(PythonKind::PydanticDataclass),
+            Target::PydanticV2BaseModel => python_class(PythonKind::PydanticV2BaseModel),
+            Target::TypedDictClass => python_class(PythonKind::TypedDict),
+            Target::TypedDictInline => python_typed_dict(false),
+            Target::NestedTypedDict => python_typed_dict(true),
+            Target::TypescriptInterface => typescript_interface(false),
+            Target::NestedTypescriptInterface => typescript_interface(true),
+            Target::RustSerde => Box::new(RustSerde {
+                indentation: indentation(),
+                to_make_public: !self.private,
+                to_narrow_integers: self.narrow_integers,
+            }),
+            Target::JsonSchema => Box::new(JSONSchema {
+                indentation: indentation(),
+            }),
+            Target::Ir => return None,
+        })
+    }
+}
+
+fn read_input(path: &PathBuf) -> io::Result<String> {
+    if path.as_os_str() == "-" {
+        let mut input = String::new();
+        io::stdin().read_to_string(&mut input)?;
+        Ok(input)
+    } else {
+        fs::read_to_string(path)
+    }
+}
+
+/// Open an input as a buffered reader, so that it can be inferred from as it is read
+fn open_input(path: &PathBuf) -> io::Result<Box<dyn Read>> {
+    if path.as_os_str() == "-" {
+        Ok(Box::new(io::stdin().lock()))
+    } else {
+        Ok(Box::new(BufReader::new(File::open(path)?)))
+    }
+}
+
+fn run(args: Args) -> Result<(), String> {
+    let mut schema = if args.json_schema {
+        let path = match args.inputs.as_slice() {
+            [path] => path,
+            _ => return Err(String::from("exactly one JSON Schema is expected")),
+        };
+        let input =
+            read_input(path).map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+        let json_schema: Value = serde_json::from_str(&input)
+            .map_err(|e| format!("{}: {}", path.display(), Error::from(e)))?;
+        infer_from_json_schema(&json_schema, args.root_name.clone()).map_err(|e| e.to_string())?
+    } else if args.ir {
+        let path = match args.inputs.as_slice() {
+            [path] => path,
+            _ => return Err(String::from("exactly one schema IR is expected")),
+        };
+        let input =
+            read_input(path).map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+        let ir: SchemaIR = serde_json::from_str(&input)
+            .map_err(|e| format!("{}: {}", path.display(), Error::from(e)))?;
+        Schema::from_ir(&ir).map_err(|e| format!("{}: {}", path.display(), e))?
+    } else {
+        let inferrer = JSONInferrer {
+            enum_detection: if args.detect_enums {
+                Some(EnumDetection {
+                    max_variants: args.enum_max_variants,
+                    min_occurrences: args.enum_min_occurrences,
+                    max_variant_ratio: args.enum_max_variant_ratio,
+                })
+            } else {
+                None
+            },
+            to_detect_tuples: !args.no_detect_tuples,
+            dict_detection: if args.no_detect_dicts {
+                None
+            } else {
+                Some(DictDetection {
+                    min_keys: args.dict_min_keys,
+                })
+            },
+            tagged_union_detection: if args.detect_tagged_unions {
+                Some(TaggedUnionDetection {
+                    max_tags: args.tagged_union_max_tags,
+                })
+            } else {
+                None
+            },
+            to_detect_string_formats: !args.no_detect_string_formats,
+            to_track_number_ranges: true,
+        };
+        // Inputs are never read into memory as a whole, so that huge ones can be handled.
+        let mut streaming = inferrer.streaming(args.root_name.clone());
+        for path in args.inputs.iter() {
+            let input = open_input(path)
+                .map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+            if args.ndjson {
+                streaming.read_many(input)
+            } else {
+                streaming.read(input)
+            }
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        }
+        streaming.finish().map_err(|e| e.to_string())?
+    };
+    // A schema IR has been optimized before it is saved, and possibly edited by hand since then.
+    if !args.ir {
+        Optimizer {
+            to_merge_similar_datatypes: !args.no_merge_similar_datatypes,
+            map_similarity: MapSimilarity {
+                threshold: args.similarity_threshold,
+                alpha: args.similarity_alpha,
+                beta: args.similarity_beta,
+            },
+            to_merge_same_unions: !args.no_merge_same_unions,
+        }
+        .optimize(&mut schema)
+        .map_err(|e| e.to_string())?;
+    }
+    let overrides = args
+        .rename
+        .iter()
+        .map(|rename| {
+            // Names never contain `=`, while paths may do in quoted keys.
+            rename
+                .rsplit_once('=')
+                .map(|(path, name)| (path.to_owned(), name.to_owned()))
+                .ok_or_else(|| format!("`{}` is not in the form of PATH=NAME", rename))
+        })
+        .collect::<Result<_, _>>()?;
+    Renamer {
+        overrides,
+        prefix: args.name_prefix.clone(),
+        suffix: args.name_suffix.clone(),
+    }
+    .rename(&mut schema)
+    .map_err(|e| e.to_string())?;
+    let output = match args.generator() {
+        Some(generator) => generator
+            .generate(&schema)
+            .map_err(|e| e.to_string())?
+            .to_string(),
+        None => {
+            let mut output = serde_json::to_string_pretty(&schema).map_err(|e| e.to_string())?;
+            output.push('\n');
+            output
+        }
+    };
+
+    match args.output {
+        Some(ref path) => fs::write(path, output)
+            .map_err(|e| format!("failed to write {}: {}", path.display(), e)),
+        None => io::stdout()
+            .write_all(output.as_bytes())
+            .map_err(|e| format!("failed to write to stdout: {}", e)),
+    }
+}
+
 fn main() {
-    println!("Hello, world!");
+    if let Err(e) = run(Args::parse()) {
+        eprintln!("json2pyi: {}", e);
+        process::exit(1);
+    }
 }
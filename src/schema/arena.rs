@@ -1,20 +1,21 @@
 pub use generational_arena::{Arena, Index as ArenaIndex};
 // pub type Arena<Type> = Arena<Type>;
-use bidirectional_map::Bimap;
 use disjoint_sets::UnionFind;
-use itertools::Itertools;
+
+use indexmap::{IndexMap, IndexSet};
 
 use std::{
-    collections::{HashMap, HashSet},
+    collections::HashMap,
+    hash::Hash,
     ops::{Deref, DerefMut},
 };
 
-use super::Type;
+use super::{Primitive, Type};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct TypeArena {
     arena: Arena<Type>,
-    primitive_types: [ArenaIndex; 9],
+    primitive_types: [ArenaIndex; Primitive::ALL.len()],
 }
 
 impl Default for TypeArena {
@@ -26,17 +27,7 @@ impl Default for TypeArena {
 impl TypeArena {
     pub fn new() -> Self {
         let mut arena = Arena::<Type>::new();
-        let primitive_types = [
-            arena.insert(Type::Int),
-            arena.insert(Type::Float),
-            arena.insert(Type::Bool),
-            arena.insert(Type::String),
-            arena.insert(Type::Date),
-            arena.insert(Type::UUID),
-            arena.insert(Type::Null),
-            arena.insert(Type::Missing),
-            arena.insert(Type::Any),
-        ];
+        let primitive_types = Primitive::ALL.map(|primitive| arena.insert(primitive.into()));
         TypeArena {
             arena,
             primitive_types,
@@ -44,54 +35,91 @@ impl TypeArena {
     }
 
     /// Get disjoint sets of similar types.
-    pub fn find_disjoint_sets<F>(
-        &self,
+    ///
+    /// Only types sharing some keys given by `keys_fn` are checked against each other by
+    /// `should_union_fn`, so that it is not needed to check every pair of types in the arena. Hence,
+    /// `should_union_fn` is expected to never hold for types sharing fewer keys than given by
+    /// `min_shared_fn` for the number of keys of either of them, which is at least one.
+    ///
+    /// Keys of every type are ordered from the rarest to the most frequent across the arena. Two
+    /// types sharing `k` keys must share one among all but the last `k - 1` keys of each (i.e. the
+    /// prefix), so only those are used to find candidates. This way, frequent keys (e.g. `id` in
+    /// every map) generate no candidates as long as a type has enough rarer keys.
+    ///
+    /// Both the sets and the types in each set are ordered as they are in the arena.
+    pub fn find_disjoint_sets<'a, K, I, G, M, F>(
+        &'a self,
+        keys_fn: G,
+        min_shared_fn: M,
         should_union_fn: F,
-    ) -> HashMap<ArenaIndex, HashSet<ArenaIndex>>
+    ) -> IndexMap<ArenaIndex, IndexSet<ArenaIndex>>
     where
+        K: Hash + Eq,
+        I: IntoIterator<Item = K>,
+        G: Fn(&'a Type) -> I,
+        M: Fn(usize) -> usize,
         F: Fn(&Type, &Type) -> bool,
     {
-        // The map between ArenaIndex and its index of type usize in the DSU
-        let imap: Bimap<usize, ArenaIndex> = Bimap::from_hash_map(
-            self.arena
-                .iter()
-                .map(|(index, _)| index)
-                .enumerate()
-                .collect(),
-        );
-        // Disjoint set union
-        let mut dsu = UnionFind::<usize>::new(imap.len());
-        {
-            let iter1 = imap.fwd().iter().map(|(&a, &b)| (a, b));
-            let iter2 = iter1.clone();
-            iter1.cartesian_product(iter2)
-        }
-        .filter(|(left, right)| left != right)
-        .filter_map(|((dsui, arni), (dsuj, arnj))| {
-            let typei = self.arena.get(arni).unwrap();
-            let typej = self.arena.get(arnj).unwrap();
-
-            if should_union_fn(typei, typej) {
-                Some((dsui, dsuj))
-            } else {
-                None
-            }
-        })
-        .for_each(|(dsui, dsuj)| {
-            // For every pair of different types in the arena, union it if should_union_fn gives true.
-            dsu.union(dsui, dsuj);
+        let arnis: Vec<ArenaIndex> = self.arena.iter().map(|(index, _)| index).collect();
+        // Keys are denoted by the order they are first seen, with their numbers of occurrences.
+        let mut ids = HashMap::<K, usize>::new();
+        let mut frequencies = Vec::<usize>::new();
+        let keys: Vec<Vec<usize>> = arnis
+            .iter()
+            .map(|&arni| {
+                keys_fn(self.arena.get(arni).unwrap())
+                    .into_iter()
+                    .map(|key| {
+                        let next = ids.len();
+                        let id = *ids.entry(key).or_insert(next);
+                        if id == frequencies.len() {
+                            frequencies.push(0);
+                        }
+                        frequencies[id] += 1;
+                        id
+                    })
+                    .collect()
+            })
+            .collect();
+        // Only the prefix of keys of every type is kept, which is never empty if any.
+        let prefixes = keys.into_iter().map(|mut keys: Vec<usize>| {
+            keys.sort_by_key(|&id| (frequencies[id], id));
+            let shared = min_shared_fn(keys.len()).clamp(1, keys.len().max(1));
+            keys.truncate((keys.len() + 1).saturating_sub(shared));
+            keys
         });
+        // Disjoint set union, where types are denoted by their positions in arnis
+        let mut dsu = UnionFind::<usize>::new(arnis.len());
+        // The inverted index from keys in prefixes to types already seen
+        let mut postings = vec![Vec::<usize>::new(); frequencies.len()];
+        // The last type for which a type has been a candidate, to avoid checking it twice
+        let mut last_candidate_of = vec![usize::MAX; arnis.len()];
+        for (dsui, prefix) in prefixes.enumerate() {
+            let typei = self.arena.get(arnis[dsui]).unwrap();
+            for key in prefix {
+                for &dsuj in postings[key].iter() {
+                    // Types already in the same set need no more checking.
+                    if last_candidate_of[dsuj] == dsui || dsu.equiv(dsui, dsuj) {
+                        continue;
+                    }
+                    last_candidate_of[dsuj] = dsui;
+                    let typej = self.arena.get(arnis[dsuj]).unwrap();
+                    // should_union_fn is not necessarily symmetric.
+                    if should_union_fn(typei, typej) || should_union_fn(typej, typei) {
+                        dsu.union(dsui, dsuj);
+                    }
+                }
+                postings[key].push(dsui);
+            }
+        }
 
         // Result sets
-        // TODO: Or just return HashSet here?
-        let mut disjoint_sets = HashMap::<ArenaIndex, HashSet<ArenaIndex>>::new();
-        for (arni, _type) in self.arena.iter() {
-            let r = imap
-                .get_rev(&arni)
-                .and_then(|&dsui| imap.get_fwd(&dsu.find(dsui)))
-                .cloned()
-                .unwrap();
-            disjoint_sets.entry(r).or_default().insert(arni);
+        let mut disjoint_sets = IndexMap::<ArenaIndex, IndexSet<ArenaIndex>>::new();
+        for (dsui, &arni) in arnis.iter().enumerate() {
+            disjoint_sets
+                .entry(arnis[dsu.find(dsui)])
+                .or_default()
+                .insert(arni);
         }
         disjoint_sets
     }
@@ -117,22 +145,16 @@ pub trait ITypeArena {
     fn remove(&mut self, i: ArenaIndex) -> Option<Type>;
     fn remove_in_favor_of(&mut self, i: ArenaIndex, j: ArenaIndex) -> Option<Type>;
     fn insert(&mut self, value: Type) -> ArenaIndex;
-    fn get_primitive_types(&self) -> &[ArenaIndex; 9];
-
-    fn get_index_of_primitive(&self, r#type: Type) -> ArenaIndex {
-        let primitive_types = self.get_primitive_types();
-        match r#type {
-            Type::Int => primitive_types[0],
-            Type::Float => primitive_types[1],
-            Type::Bool => primitive_types[2],
-            Type::String => primitive_types[3],
-            Type::Date => primitive_types[4],
-            Type::UUID => primitive_types[5],
-            Type::Null => primitive_types[6],
-            Type::Missing => primitive_types[7],
-            Type::Any => primitive_types[8],
-            _ => panic!("Not a primitive type: {:?}", r#type),
-        }
+    fn get_primitive_types(&self) -> &[ArenaIndex; Primitive::ALL.len()];
+
+    /// Get the index that `i` currently stands for, which differs from `i` only if the type has
+    /// been removed in favor of another one
+    fn resolve(&self, i: ArenaIndex) -> ArenaIndex {
+        i
+    }
+
+    fn get_index_of_primitive(&self, primitive: Primitive) -> ArenaIndex {
+        self.get_primitive_types()[primitive as usize]
     }
 }
 
@@ -164,7 +186,7 @@ impl ITypeArena for TypeArena {
     }
 
     #[inline(always)]
-    fn get_primitive_types(&self) -> &[ArenaIndex; 9] {
+    fn get_primitive_types(&self) -> &[ArenaIndex; Primitive::ALL.len()] {
         &self.primitive_types
     }
 }
@@ -0,0 +1,37 @@
+use indexmap::IndexMap;
+
+use std::fmt::{self, Display};
+
+use super::name_hints::NameHints;
+
+/// A string type with a limited set of possible values, with hints for its name
+///
+/// Generally, it is inferred from [`serde_json::Value::String`]s at the same position that share
+/// only a few distinct values (e.g. `"status": "active"`). It is usually generated as `Enum` (as in
+/// Python), `enum` (as in Rust) or a union of string literals (as in TypeScript).
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Enum {
+    pub name_hints: NameHints,
+    /// The unique name resolved from `name_hints` by [`Schema::resolve_names`](super::Schema::resolve_names)
+    pub name: Option<String>,
+    /// Distinct values and their numbers of occurrences, in the order they are first seen
+    pub variants: IndexMap<String, usize>,
+}
+
+impl Enum {
+    /// Get the total number of occurrences of all variants
+    pub fn occurrences(&self) -> usize {
+        self.variants.values().sum()
+    }
+}
+
+impl Display for Enum {
+    /// Display the unique name if resolved, or the name hints otherwise
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.name {
+            Some(ref name) => name.fmt(f),
+            None if self.name_hints.is_empty() => write!(f, "UnnamedEnum"),
+            None => self.name_hints.fmt(f),
+        }
+    }
+}
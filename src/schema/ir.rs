@@ -0,0 +1,373 @@
+use indexmap::{IndexMap, IndexSet};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use std::collections::{HashMap, HashSet};
+
+use super::{
+    arena::{ArenaIndex, ITypeArena, TypeArena},
+    dedup,
+    name_hints::NameHints,
+    Enum, Map, Number, Primitive, Schema, Type, Union,
+};
+use crate::error::{Error, Result};
+
+/// The version of the representation, bumped on every incompatible change
+pub const IR_VERSION: u32 = 1;
+
+/// A serializable representation of a [`Schema`], for it to be saved, edited by hand and loaded
+///
+/// Instead of arena indices, types refer to each other by keys of `definitions`. Maps and enums are
+/// always defined there, as are other types referenced more than once, so that the structure of
+/// the schema is kept as is. Other types are inlined where they are referenced. Named types are
+/// keyed by their unique names (see [`Schema::unique_names`]).
+///
+/// In JSON, a primitive type is a string (e.g. `"int"` or `"uuid"`), while others are objects with
+/// a single key telling their kinds, e.g. `{"ref": "User"}`, `{"array": "string"}`,
+/// `{"dict": ["string", "int"]}` or `{"map": {"name_hints": ["User"], "fields": {...}}}`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SchemaIR {
+    pub version: u32,
+    pub root: TypeIR,
+    /// Named types, in the order they are first seen from the root
+    #[serde(default)]
+    pub definitions: IndexMap<String, TypeIR>,
+}
+
+/// A type in a [`SchemaIR`], which mirrors [`Type`] with inner types given in place
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TypeIR {
+    /// A reference to the type defined under the key in [`SchemaIR::definitions`]
+    Ref(String),
+    Map {
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        name_hints: Vec<String>,
+        fields: IndexMap<String, TypeIR>,
+    },
+    Array(Box<TypeIR>),
+    Tuple(Vec<TypeIR>),
+    Dict(Box<TypeIR>, Box<TypeIR>),
+    Union {
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        name_hints: Vec<String>,
+        types: Vec<TypeIR>,
+    },
+    Enum {
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        name_hints: Vec<String>,
+        variants: IndexMap<String, usize>,
+    },
+    Number(Number),
+    Int,
+    Float,
+    Bool,
+    String,
+    Date,
+    #[serde(rename = "uuid")]
+    UUID,
+    PlainDate,
+    Time,
+    Duration,
+    #[serde(rename = "url")]
+    URL,
+    Email,
+    #[serde(rename = "ip_address")]
+    IPAddress,
+    Decimal,
+    Bytes,
+    Null,
+    Missing,
+    Any,
+}
+
+impl TypeIR {
+    /// Get the primitive type it stands for, if any
+    pub fn as_primitive(&self) -> Option<Primitive> {
+        match *self {
+            TypeIR::Int => Some(Primitive::Int),
+            TypeIR::Float => Some(Primitive::Float),
+            TypeIR::Bool => Some(Primitive::Bool),
+            TypeIR::String => Some(Primitive::String),
+            TypeIR::Date => Some(Primitive::Date),
+            TypeIR::UUID => Some(Primitive::UUID),
+            TypeIR::PlainDate => Some(Primitive::PlainDate),
+            TypeIR::Time => Some(Primitive::Time),
+            TypeIR::Duration => Some(Primitive::Duration),
+            TypeIR::URL => Some(Primitive::URL),
+            TypeIR::Email => Some(Primitive::Email),
+            TypeIR::IPAddress => Some(Primitive::IPAddress),
+            TypeIR::Decimal => Some(Primitive::Decimal),
+            TypeIR::Bytes => Some(Primitive::Bytes),
+            TypeIR::Null => Some(Primitive::Null),
+            TypeIR::Missing => Some(Primitive::Missing),
+            TypeIR::Any => Some(Primitive::Any),
+            _ => None,
+        }
+    }
+}
+
+impl Schema {
+    /// Convert the schema into its serializable representation
+    pub fn to_ir(&self) -> SchemaIR {
+        // Types are counted once per referrer, with the root referenced by the schema itself.
+        let mut references = HashMap::<ArenaIndex, usize>::new();
+        *references.entry(self.root).or_default() += 1;
+        for arni in self.iter_topdown() {
+            for inner in self.arena.get(arni).unwrap().inner_types() {
+                *references.entry(inner).or_default() += 1;
+            }
+        }
+
+        // Named types are keyed by their unique names, which other types are kept apart from.
+        let names = self.unique_names(&[]);
+        let mut seen: HashSet<String> = names.values().cloned().collect();
+        let mut defs = IndexMap::<ArenaIndex, String>::new();
+        for arni in self.iter_topdown() {
+            let key = match *self.arena.get(arni).unwrap() {
+                Type::Map(_) | Type::Enum(_) => names[&arni].to_owned(),
+                _ if references[&arni] < 2 => continue,
+                Type::Union(_) => names[&arni].to_owned(),
+                Type::Array(_) => dedup(&mut seen, String::from("Array")),
+                Type::Tuple(_) => dedup(&mut seen, String::from("Tuple")),
+                Type::Dict(_, _) => dedup(&mut seen, String::from("Dict")),
+                Type::Number(_) => dedup(&mut seen, String::from("Number")),
+                // Primitive types are always shared in the arena.
+                _ => continue,
+            };
+            defs.insert(arni, key);
+        }
+
+        let converter = Converter {
+            schema: self,
+            defs: &defs,
+        };
+        SchemaIR {
+            version: IR_VERSION,
+            root: converter.convert(self.root),
+            definitions: defs
+                .iter()
+                .map(|(&arni, key)| (key.to_owned(), converter.convert_def(arni)))
+                .collect(),
+        }
+    }
+
+    /// Load a schema from its serializable representation
+    ///
+    /// Only types reachable from the root are loaded, while unused definitions are ignored.
+    pub fn from_ir(ir: &SchemaIR) -> Result<Schema> {
+        if ir.version != IR_VERSION {
+            return Err(Error::InvalidIR(format!(
+                "unsupported version {}, expected {}",
+                ir.version, IR_VERSION
+            )));
+        }
+        let mut loader = Loader {
+            arena: TypeArena::new(),
+            definitions: &ir.definitions,
+            loaded: HashMap::new(),
+        };
+        let root = loader.load(&ir.root)?;
+        Ok(Schema {
+            arena: loader.arena,
+            root,
+        })
+    }
+}
+
+impl Serialize for Schema {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        self.to_ir().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Schema {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let ir = SchemaIR::deserialize(deserializer)?;
+        Schema::from_ir(&ir).map_err(de::Error::custom)
+    }
+}
+
+struct Converter<'a> {
+    schema: &'a Schema,
+    defs: &'a IndexMap<ArenaIndex, String>,
+}
+
+impl<'a> Converter<'a> {
+    /// Convert a type into a reference if it is defined, or inline it
+    fn convert(&self, arni: ArenaIndex) -> TypeIR {
+        match self.defs.get(&arni) {
+            Some(key) => TypeIR::Ref(key.to_owned()),
+            None => self.convert_def(arni),
+        }
+    }
+
+    /// Convert a type as is, with its inner types converted by `convert`
+    fn convert_def(&self, arni: ArenaIndex) -> TypeIR {
+        let name_hints = |name_hints: &NameHints| name_hints.iter().cloned().collect();
+        match *self.schema.arena.get(arni).unwrap() {
+            Type::Map(ref map) => TypeIR::Map {
+                name_hints: name_hints(&map.name_hints),
+                fields: map
+                    .fields
+                    .iter()
+                    .map(|(key, &r#type)| (key.to_owned(), self.convert(r#type)))
+                    .collect(),
+            },
+            Type::Array(inner) => TypeIR::Array(Box::new(self.convert(inner))),
+            Type::Tuple(ref types) => {
+                TypeIR::Tuple(types.iter().map(|&r#type| self.convert(r#type)).collect())
+            }
+            Type::Dict(key, value) => {
+                TypeIR::Dict(Box::new(self.convert(key)), Box::new(self.convert(value)))
+            }
+            Type::Union(ref union) => TypeIR::Union {
+                name_hints: name_hints(&union.name_hints),
+                types: union
+                    .types
+                    .iter()
+                    .map(|&r#type| self.convert(r#type))
+                    .collect(),
+            },
+            Type::Enum(ref r#enum) => TypeIR::Enum {
+                name_hints: name_hints(&r#enum.name_hints),
+                variants: r#enum.variants.clone(),
+            },
+            Type::Number(number) => TypeIR::Number(number),
+            Type::Int => TypeIR::Int,
+            Type::Float => TypeIR::Float,
+            Type::Bool => TypeIR::Bool,
+            Type::String => TypeIR::String,
+            Type::Date => TypeIR::Date,
+            Type::UUID => TypeIR::UUID,
+            Type::PlainDate => TypeIR::PlainDate,
+            Type::Time => TypeIR::Time,
+            Type::Duration => TypeIR::Duration,
+            Type::URL => TypeIR::URL,
+            Type::Email => TypeIR::Email,
+            Type::IPAddress => TypeIR::IPAddress,
+            Type::Decimal => TypeIR::Decimal,
+            Type::Bytes => TypeIR::Bytes,
+            Type::Null => TypeIR::Null,
+            Type::Missing => TypeIR::Missing,
+            Type::Any => TypeIR::Any,
+        }
+    }
+}
+
+struct Loader<'a> {
+    arena: TypeArena,
+    definitions: &'a IndexMap<String, TypeIR>,
+    /// Definitions loaded so far, or being loaded if `None`
+    loaded: HashMap<&'a str, Option<ArenaIndex>>,
+}
+
+impl<'a> Loader<'a> {
+    /// Load a type into the arena, with primitive types mapped to their shared slots
+    fn load(&mut self, ir: &'a TypeIR) -> Result<ArenaIndex> {
+        if let TypeIR::Ref(ref key) = *ir {
+            return self.load_def(key);
+        }
+        if let Some(primitive) = ir.as_primitive() {
+            return Ok(self.arena.get_index_of_primitive(primitive));
+        }
+        let arni = self.arena.insert(Type::Any);
+        self.fill(arni, ir)?;
+        Ok(arni)
+    }
+
+    /// Load a definition, which is loaded only once however many times it is referenced
+    fn load_def(&mut self, key: &'a str) -> Result<ArenaIndex> {
+        match self.loaded.get(key) {
+            Some(&Some(arni)) => return Ok(arni),
+            Some(&None) => {
+                return Err(Error::InvalidIR(format!(
+                    "`{}` is defined as a reference to itself",
+                    key
+                )))
+            }
+            None => (),
+        }
+        let ir = self
+            .definitions
+            .get(key)
+            .ok_or_else(|| Error::InvalidIR(format!("`{}` is not defined", key)))?;
+        let arni = match *ir {
+            TypeIR::Ref(_) => {
+                self.loaded.insert(key, None);
+                self.load(ir)?
+            }
+            _ if ir.as_primitive().is_some() => self.load(ir)?,
+            _ => {
+                // The slot is taken in advance, so that recursive references resolve to it.
+                let arni = self.arena.insert(Type::Any);
+                self.loaded.insert(key, Some(arni));
+                self.fill(arni, ir)?;
+                arni
+            }
+        };
+        self.loaded.insert(key, Some(arni));
+        Ok(arni)
+    }
+
+    /// Fill a slot with a non-primitive type
+    fn fill(&mut self, arni: ArenaIndex, ir: &'a TypeIR) -> Result<()> {
+        let r#type = match *ir {
+            TypeIR::Map {
+                ref name_hints,
+                ref fields,
+            } => Type::Map(Map {
+                name_hints: to_name_hints(name_hints),
+                name: None,
+                fields: fields
+                    .iter()
+                    .map(|(key, r#type)| Ok((key.to_owned(), self.load(r#type)?)))
+                    .collect::<Result<_>>()?,
+            }),
+            TypeIR::Array(ref inner) => Type::Array(self.load(inner)?),
+            TypeIR::Tuple(ref types) => Type::Tuple(
+                types
+                    .iter()
+                    .map(|r#type| self.load(r#type))
+                    .collect::<Result<_>>()?,
+            ),
+            TypeIR::Dict(ref key, ref value) => Type::Dict(self.load(key)?, self.load(value)?),
+            TypeIR::Union {
+                ref name_hints,
+                ref types,
+            } => {
+                let types: IndexSet<ArenaIndex> = types
+                    .iter()
+                    .map(|r#type| self.load(r#type))
+                    .collect::<Result<_>>()?;
+                // Generators expect some member other than `Missing`, as inferred unions have.
+                let missing = self.arena.get_index_of_primitive(Primitive::Missing);
+                if types.iter().all(|&arni| arni == missing) {
+                    return Err(Error::InvalidIR(String::from(
+                        "a union has no member other than `missing`",
+                    )));
+                }
+                Type::Union(Union {
+                    name_hints: to_name_hints(name_hints),
+                    name: None,
+                    types,
+                })
+            }
+            TypeIR::Enum {
+                ref name_hints,
+                ref variants,
+            } => Type::Enum(Enum {
+                name_hints: to_name_hints(name_hints),
+                name: None,
+                variants: variants.clone(),
+            }),
+            TypeIR::Number(number) => Type::Number(number),
+            _ => unreachable!("References and primitive types take no slot of their own"),
+        };
+        *self.arena.get_mut(arni).unwrap() = r#type;
+        Ok(())
+    }
+}
+
+fn to_name_hints(name_hints: &[String]) -> NameHints {
+    name_hints.iter().cloned().collect::<IndexSet<_>>().into()
+}
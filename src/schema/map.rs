@@ -5,7 +5,11 @@ use std::{
     fmt::{self, Display},
 };
 
-use super::{arena::ArenaIndex, name_hints::NameHints};
+use super::{
+    arena::{ArenaIndex, ITypeArena},
+    name_hints::NameHints,
+    Type,
+};
 
 /// A collection of field names and their corresponding types, with hints for its name
 ///
@@ -15,47 +19,66 @@ use super::{arena::ArenaIndex, name_hints::NameHints};
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Map {
     pub name_hints: NameHints, // FIX: IndexMap to ensure name generation is the same all the time
+    /// The unique name resolved from `name_hints` by [`Schema::resolve_names`](super::Schema::resolve_names)
+    pub name: Option<String>,
     pub fields: IndexMap<String, ArenaIndex>,
 }
 
 impl Map {
-    /// Compare the structure of two `Map`s to determine if they are similar enough to be merged
-    pub fn is_similar_to(&self, other: &Self) -> bool {
-        // TODO: take value type into consideration
-        let a: HashSet<_> = self
-            .fields
-            .iter()
-            .map(|(name, _)| name)
-            // filter out dictionary-style array
-            // e.g. https://github.com/Gowee/json2pyi/issues/8#issue-1835399209
-            .filter(|name| !name.chars().all(|c| c.is_ascii_digit()))
-            .collect();
-        let b: HashSet<_> = other
-            .fields
-            .iter()
-            .map(|(name, _)| name)
-            // ditto
+    /// Get the value of a field that is a constant string (i.e. an enum of a single variant),
+    /// along with its number of occurrences, as a candidate of the tag in a tagged union
+    pub fn tag<'a>(&self, key: &str, arena: &'a impl ITypeArena) -> Option<(&'a str, usize)> {
+        match self.fields.get(key).and_then(|&arni| arena.get(arni)) {
+            Some(Type::Enum(r#enum)) if r#enum.variants.len() == 1 => r#enum
+                .variants
+                .first()
+                .map(|(value, &count)| (value.as_str(), count)),
+            _ => None,
+        }
+    }
+
+    /// Get the keys of fields compared by [`Map::tversky_index`], i.e. other than indices
+    pub fn compared_keys(&self) -> impl Iterator<Item = &String> {
+        // filter out dictionary-style array
+        // e.g. https://github.com/Gowee/json2pyi/issues/8#issue-1835399209
+        self.fields
+            .keys()
             .filter(|name| !name.chars().all(|c| c.is_ascii_digit()))
-            .collect();
+    }
 
-        let a_intsec_b = a.intersection(&b).count();
-        let a_diff_b = a.difference(&b).count();
-        let b_diff_a = b.difference(&a).count();
-        let tversky_index =
-            a_intsec_b as f64 / (a_intsec_b as f64 + 1.0 * a_diff_b as f64 + 1.0 * b_diff_a as f64);
+    /// Compute the [Tversky index](https://en.wikipedia.org/wiki/Tversky_index) of two `Map`s
+    /// over their fields, where `alpha` and `beta` weigh fields only in `self` and only in
+    /// `other` respectively
+    ///
+    /// A field in common is counted as a difference on both sides if its types are not alike as
+    /// determined by `are_alike`.
+    pub fn tversky_index(
+        &self,
+        other: &Self,
+        alpha: f64,
+        beta: f64,
+        are_alike: impl Fn(ArenaIndex, ArenaIndex) -> bool,
+    ) -> f64 {
+        let a: HashSet<_> = self.compared_keys().collect();
+        let b: HashSet<_> = other.compared_keys().collect();
 
-        // https://en.wikipedia.org/wiki/Tversky_index
-        tversky_index > 0.8
+        let (alike, unalike): (Vec<&String>, Vec<&String>) = a
+            .intersection(&b)
+            .partition(|&&name| are_alike(self.fields[name], other.fields[name]));
+        let a_intsec_b = alike.len() as f64;
+        let a_diff_b = (a.difference(&b).count() + unalike.len()) as f64;
+        let b_diff_a = (b.difference(&a).count() + unalike.len()) as f64;
+        a_intsec_b / (a_intsec_b + alpha * a_diff_b + beta * b_diff_a)
     }
 }
 
 impl Display for Map {
+    /// Display the unique name if resolved, or the name hints otherwise
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        if self.name_hints.is_empty() {
-            // NOTE: the type wrapper of map should be stored in Arena for persistent memory address.
-            write!(f, "UnnammedType{:X}", self as *const Map as usize)
-        } else {
-            self.name_hints.fmt(f)
+        match self.name {
+            Some(ref name) => name.fmt(f),
+            None if self.name_hints.is_empty() => write!(f, "UnnamedType"),
+            None => self.name_hints.fmt(f),
         }
     }
 }
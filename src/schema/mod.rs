@@ -3,21 +3,30 @@ use indexmap::IndexSet;
 use std::collections::HashSet;
 
 mod arena;
+mod r#enum;
+mod ir;
 mod map;
 mod name_hints;
+mod naming;
+mod number;
 mod union;
 
+pub(crate) use self::naming::dedup;
 pub use self::{
     arena::{Arena, ArenaIndex, ITypeArena, TypeArena},
+    ir::{SchemaIR, TypeIR, IR_VERSION},
     map::Map,
     name_hints::NameHints,
+    number::Number,
+    r#enum::Enum,
     union::Union,
 };
 
 /// A schema inferred from a sample JSON
 ///
 /// It is a wrapper around [`TypeArena`] with a additional `root` field pointing to the root type.
-#[derive(Debug)]
+/// It is (de)serialized as a [`SchemaIR`].
+#[derive(Debug, Clone)]
 pub struct Schema {
     pub arena: TypeArena,
     pub root: ArenaIndex,
@@ -28,19 +37,111 @@ pub enum Type {
     // TODO: doc
     Map(Map),
     Array(ArenaIndex),
+    Tuple(Vec<ArenaIndex>),
+    /// A dictionary-style object with arbitrary keys (e.g. IDs) of the first type and values of
+    /// the second type
+    Dict(ArenaIndex, ArenaIndex),
     Union(Union),
+    Enum(Enum),
+    /// An `Int` or `Float` with the range of values observed at its position
+    Number(Number),
     Int,
     Float,
     Bool,
     String,
+    /// A string of a date with time, e.g. `2024-01-31T08:00:00Z`
     Date,
     UUID,
+    /// A string of a date without time, e.g. `2024-01-31`
+    PlainDate,
+    /// A string of a time of day, e.g. `08:00:00`
+    Time,
+    /// A string of an ISO 8601 duration, e.g. `P1DT2H`
+    Duration,
+    /// A string of an absolute URL, e.g. `https://example.com/`
+    URL,
+    /// A string of an email address
+    Email,
+    /// A string of an IPv4 or IPv6 address
+    IPAddress,
+    /// A string of a decimal number, e.g. `"12.50"` for an amount of money
+    Decimal,
+    /// A string of Base64-encoded binary data
+    Bytes,
     Null,
     Missing,
     #[default]
     Any,
 }
 
+/// A type without any inner types, each of which has a single shared slot in a [`TypeArena`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Primitive {
+    Int,
+    Float,
+    Bool,
+    String,
+    Date,
+    UUID,
+    PlainDate,
+    Time,
+    Duration,
+    URL,
+    Email,
+    IPAddress,
+    Decimal,
+    Bytes,
+    Null,
+    Missing,
+    Any,
+}
+
+impl Primitive {
+    pub const ALL: [Primitive; 17] = [
+        Primitive::Int,
+        Primitive::Float,
+        Primitive::Bool,
+        Primitive::String,
+        Primitive::Date,
+        Primitive::UUID,
+        Primitive::PlainDate,
+        Primitive::Time,
+        Primitive::Duration,
+        Primitive::URL,
+        Primitive::Email,
+        Primitive::IPAddress,
+        Primitive::Decimal,
+        Primitive::Bytes,
+        Primitive::Null,
+        Primitive::Missing,
+        Primitive::Any,
+    ];
+}
+
+impl From<Primitive> for Type {
+    fn from(primitive: Primitive) -> Self {
+        match primitive {
+            Primitive::Int => Type::Int,
+            Primitive::Float => Type::Float,
+            Primitive::Bool => Type::Bool,
+            Primitive::String => Type::String,
+            Primitive::Date => Type::Date,
+            Primitive::UUID => Type::UUID,
+            Primitive::PlainDate => Type::PlainDate,
+            Primitive::Time => Type::Time,
+            Primitive::Duration => Type::Duration,
+            Primitive::URL => Type::URL,
+            Primitive::Email => Type::Email,
+            Primitive::IPAddress => Type::IPAddress,
+            Primitive::Decimal => Type::Decimal,
+            Primitive::Bytes => Type::Bytes,
+            Primitive::Null => Type::Null,
+            Primitive::Missing => Type::Missing,
+            Primitive::Any => Type::Any,
+        }
+    }
+}
+
 pub struct TopdownIter<'a> {
     arena: &'a TypeArena,
     stack: Vec<ArenaIndex>,
@@ -73,6 +174,15 @@ impl<'a> Iterator for TopdownIter<'a> {
                 Type::Array(inner) => {
                     pick(inner);
                 }
+                Type::Tuple(ref types) => {
+                    for &r#type in types.iter().rev() {
+                        pick(r#type);
+                    }
+                }
+                Type::Dict(key, value) => {
+                    pick(value);
+                    pick(key);
+                }
                 Type::Union(ref union) => {
                     for &r#type in union.types.iter() {
                         pick(r#type);
@@ -89,7 +199,7 @@ impl<'a> Iterator for TopdownIter<'a> {
 
 impl Schema {
     /// Iterate over all types in the schema from its `root`
-    pub fn iter_topdown(&self) -> TopdownIter {
+    pub fn iter_topdown(&self) -> TopdownIter<'_> {
         // TODO: iterate in topological order by BFS
         //       which needs a predicate fn to determine whether to flat a union/map in its level
         let arena = &self.arena;
@@ -140,6 +250,15 @@ impl Schema {
                 Type::Array(inner) => {
                     pick(inner);
                 }
+                Type::Tuple(ref types) => {
+                    for &r#type in types.iter().rev() {
+                        pick(r#type);
+                    }
+                }
+                Type::Dict(key, value) => {
+                    pick(value);
+                    pick(key);
+                }
                 Type::Union(ref union) => {
                     for &r#type in union.types.iter() {
                         pick(r#type);
@@ -199,6 +318,23 @@ impl Type {
         matches!(*self, Self::String)
     }
 
+    /// Check if it is a string with a specific format (e.g. `Date`), as opposed to `String`
+    pub fn is_formatted_string(&self) -> bool {
+        matches!(
+            *self,
+            Self::Date
+                | Self::UUID
+                | Self::PlainDate
+                | Self::Time
+                | Self::Duration
+                | Self::URL
+                | Self::Email
+                | Self::IPAddress
+                | Self::Decimal
+                | Self::Bytes
+        )
+    }
+
     pub fn is_any(&self) -> bool {
         matches!(*self, Self::Any)
     }
@@ -221,6 +357,42 @@ impl Type {
         self.as_array().is_some()
     }
 
+    pub fn into_tuple(self) -> Option<Vec<ArenaIndex>> {
+        match self {
+            Self::Tuple(types) => Some(types),
+            _ => None,
+        }
+    }
+
+    pub fn as_tuple(&self) -> Option<&[ArenaIndex]> {
+        match *self {
+            Self::Tuple(ref types) => Some(types),
+            _ => None,
+        }
+    }
+
+    pub fn is_tuple(&self) -> bool {
+        self.as_tuple().is_some()
+    }
+
+    pub fn into_dict(self) -> Option<(ArenaIndex, ArenaIndex)> {
+        match self {
+            Self::Dict(key, value) => Some((key, value)),
+            _ => None,
+        }
+    }
+
+    pub fn as_dict(&self) -> Option<(ArenaIndex, ArenaIndex)> {
+        match *self {
+            Self::Dict(key, value) => Some((key, value)),
+            _ => None,
+        }
+    }
+
+    pub fn is_dict(&self) -> bool {
+        self.as_dict().is_some()
+    }
+
     pub fn into_union(self) -> Option<Union> {
         match self {
             Self::Union(types) => Some(types),
@@ -245,4 +417,87 @@ impl Type {
     pub fn is_union(&self) -> bool {
         self.as_union().is_some()
     }
+
+    pub fn into_enum(self) -> Option<Enum> {
+        match self {
+            Self::Enum(r#enum) => Some(r#enum),
+            _ => None,
+        }
+    }
+
+    pub fn as_enum(&self) -> Option<&Enum> {
+        match *self {
+            Self::Enum(ref r#enum) => Some(r#enum),
+            _ => None,
+        }
+    }
+
+    pub fn is_enum(&self) -> bool {
+        self.as_enum().is_some()
+    }
+
+    pub fn into_number(self) -> Option<Number> {
+        match self {
+            Self::Number(number) => Some(number),
+            _ => None,
+        }
+    }
+
+    pub fn as_number(&self) -> Option<&Number> {
+        match *self {
+            Self::Number(ref number) => Some(number),
+            _ => None,
+        }
+    }
+
+    pub fn is_number(&self) -> bool {
+        self.as_number().is_some()
+    }
+
+    /// Get the name hints of a named type (i.e. a `Map`, a `Union` or an `Enum`)
+    pub fn name_hints_mut(&mut self) -> Option<&mut NameHints> {
+        match *self {
+            Self::Map(ref mut map) => Some(&mut map.name_hints),
+            Self::Union(ref mut union) => Some(&mut union.name_hints),
+            Self::Enum(ref mut r#enum) => Some(&mut r#enum.name_hints),
+            _ => None,
+        }
+    }
+
+    /// Get arena indices of all inner types (e.g. fields of a map), in the order they are declared
+    pub fn inner_types(&self) -> Vec<ArenaIndex> {
+        match *self {
+            Type::Map(ref map) => map.fields.values().cloned().collect(),
+            Type::Array(inner) => vec![inner],
+            Type::Dict(key, value) => vec![key, value],
+            Type::Tuple(ref types) => types.clone(),
+            Type::Union(ref union) => union.types.iter().cloned().collect(),
+            _ => vec![],
+        }
+    }
+
+    /// Replace arena indices of all inner types (e.g. fields of a map) in place
+    pub fn replace_inner_types(&mut self, replace: impl Fn(ArenaIndex) -> ArenaIndex) {
+        match *self {
+            Type::Map(ref mut map) => {
+                for (_, r#type) in map.fields.iter_mut() {
+                    *r#type = replace(*r#type);
+                }
+            }
+            Type::Array(ref mut inner) => *inner = replace(*inner),
+            Type::Dict(ref mut key, ref mut value) => {
+                *key = replace(*key);
+                *value = replace(*value);
+            }
+            Type::Tuple(ref mut types) => {
+                for r#type in types.iter_mut() {
+                    *r#type = replace(*r#type);
+                }
+            }
+            Type::Union(ref mut union) => {
+                union.types = union.types.iter().cloned().map(replace).collect();
+            }
+            _ => (),
+        }
+    }
 }
@@ -0,0 +1,171 @@
+use indexmap::IndexMap;
+use inflector::Inflector;
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use super::{ArenaIndex, ITypeArena, Primitive, Schema, Type, Union};
+
+impl Schema {
+    /// Get unique names of all named types (i.e. `Map`s, `Union`s and `Enum`s) reachable from the
+    /// root, in the order they are reached level by level
+    ///
+    /// A type is named after its name hints. If some types share the same name hints, each of them
+    /// is prefixed with the name of the closest map that it is found in (e.g. `UserData` and
+    /// `OrderData`), and suffixed with a sequence number if it is still taken. Maps and enums are
+    /// named before unions, so that they are never renamed because of a union. Types without any
+    /// name hints come last, named `Root` for the root and maps not found in any other map, or
+    /// after the field of the closest map that they are found in (e.g. `OrderTotal`) for others.
+    ///
+    /// `reserved` names (e.g. imports of a target) are never taken, as if they were seen already.
+    ///
+    /// The names only depend on the structure of the schema, so that they are the same across runs.
+    pub fn unique_names(&self, reserved: &[&str]) -> IndexMap<ArenaIndex, String> {
+        // The closest map above every type when it is first reached, in BFS order
+        let mut parents = IndexMap::<ArenaIndex, Option<ArenaIndex>>::new();
+        // The field of the closest map through which every type is first reached
+        let mut keys = HashMap::<ArenaIndex, &str>::new();
+        parents.insert(self.root, None);
+        let mut queue: VecDeque<ArenaIndex> = vec![self.root].into();
+        while let Some(arni) = queue.pop_front() {
+            let r#type = self.arena.get(arni).unwrap();
+            let (parent, inners): (_, Vec<(ArenaIndex, Option<&str>)>) = match *r#type {
+                Type::Map(ref map) => (
+                    Some(arni),
+                    map.fields
+                        .iter()
+                        .map(|(key, &inner)| (inner, Some(key.as_str())))
+                        .collect(),
+                ),
+                _ => (
+                    parents[&arni],
+                    r#type
+                        .inner_types()
+                        .into_iter()
+                        .map(|inner| (inner, keys.get(&arni).copied()))
+                        .collect(),
+                ),
+            };
+            for (inner, key) in inners {
+                if !parents.contains_key(&inner) {
+                    parents.insert(inner, parent);
+                    if let Some(key) = key {
+                        keys.insert(inner, key);
+                    }
+                    queue.push_back(inner);
+                }
+            }
+        }
+
+        let mut names = IndexMap::<ArenaIndex, String>::new();
+        let mut seen: HashSet<String> = reserved.iter().map(|&name| name.to_owned()).collect();
+        for is_union_turn in [false, true] {
+            let types: Vec<(ArenaIndex, &Type)> = parents
+                .keys()
+                .map(|&arni| (arni, self.arena.get(arni).unwrap()))
+                .filter(|&(_, r#type)| match *r#type {
+                    Type::Map(_) | Type::Enum(_) => !is_union_turn,
+                    Type::Union(_) => is_union_turn,
+                    _ => false,
+                })
+                .collect();
+            let hinted: Vec<(ArenaIndex, String)> = types
+                .iter()
+                .filter_map(|&(arni, r#type)| {
+                    let name_hints = match *r#type {
+                        Type::Map(ref map) => &map.name_hints,
+                        Type::Union(ref union) => &union.name_hints,
+                        Type::Enum(ref r#enum) => &r#enum.name_hints,
+                        _ => unreachable!(),
+                    };
+                    (!name_hints.is_empty()).then(|| (arni, name_hints.to_string()))
+                })
+                .collect();
+            let mut counts = HashMap::<&str, usize>::new();
+            for (_, name) in hinted.iter() {
+                *counts.entry(name).or_default() += 1;
+            }
+            // Names that are not shared are taken first, so that no disambiguated name takes them.
+            for (arni, name) in hinted.iter() {
+                if counts[name.as_str()] == 1 && seen.insert(name.to_owned()) {
+                    names.insert(*arni, name.to_owned());
+                }
+            }
+            // Parents are named before their children as they are reached earlier, unless they
+            // have no name hints.
+            for (arni, name) in hinted.iter() {
+                if !names.contains_key(arni) {
+                    let name = match parents[arni].and_then(|parent| names.get(&parent)) {
+                        Some(parent) => format!("{}{}", parent, name),
+                        None => name.to_owned(),
+                    };
+                    names.insert(*arni, dedup(&mut seen, name));
+                }
+            }
+            let mut unnamed: Vec<(ArenaIndex, &Type)> = types
+                .into_iter()
+                .filter(|(arni, _)| !names.contains_key(arni))
+                .collect();
+            // Optional types (e.g. `T | null`) are rarely written out by name, so they are numbered
+            // after other unions.
+            unnamed.sort_by_key(|&(_, r#type)| {
+                r#type
+                    .as_union()
+                    .map_or(false, |union| !self.is_non_trivial(union))
+            });
+            for (arni, r#type) in unnamed {
+                let parent = parents[&arni].and_then(|parent| names.get(&parent));
+                let field = keys
+                    .get(&arni)
+                    .map(|key| key.to_pascal_case())
+                    .filter(|field| !field.is_empty());
+                let name = match (r#type, parent, field) {
+                    _ if arni == self.root => String::from("Root"),
+                    (Type::Map(_), _, _) if parents[&arni].is_none() => String::from("Root"),
+                    (_, Some(parent), Some(field)) => format!("{}{}", parent, field),
+                    (Type::Map(_), _, _) => String::from("UnnamedType"),
+                    (Type::Union(_), _, _) => String::from("UnnamedUnion"),
+                    _ => String::from("UnnamedEnum"),
+                };
+                names.insert(arni, dedup(&mut seen, name));
+            }
+        }
+        names
+    }
+
+    /// Check if a union has more than one type other than `Null` and `Missing`
+    fn is_non_trivial(&self, union: &Union) -> bool {
+        let optional = [Primitive::Null, Primitive::Missing]
+            .iter()
+            .filter(|&&primitive| {
+                union
+                    .types
+                    .contains(&self.arena.get_index_of_primitive(primitive))
+            })
+            .count();
+        union.types.len() - optional > 1
+    }
+
+    /// Assign the unique names given by [`Schema::unique_names`] to named types, which are used by
+    /// target generators
+    pub fn resolve_names(&mut self, reserved: &[&str]) {
+        for (arni, name) in self.unique_names(reserved) {
+            match *self.arena.get_mut(arni).unwrap() {
+                Type::Map(ref mut map) => map.name = Some(name),
+                Type::Union(ref mut union) => union.name = Some(name),
+                Type::Enum(ref mut r#enum) => r#enum.name = Some(name),
+                _ => unreachable!(),
+            }
+        }
+    }
+}
+
+/// Suffix a name with a sequence number in case of collision
+pub(crate) fn dedup(seen: &mut HashSet<String>, name: String) -> String {
+    let mut candidate = name.clone();
+    let mut i = 1;
+    while !seen.insert(candidate.clone()) {
+        i += 1;
+        candidate = format!("{}{}", name, i);
+    }
+    candidate
+}
@@ -0,0 +1,57 @@
+use serde::{Deserialize, Serialize};
+
+/// The largest integer that is exactly representable in a JavaScript `number` (i.e. `f64`)
+const MAX_SAFE_INTEGER: f64 = 9007199254740991.0;
+
+/// A number type with the range and the integrality of values observed at its position
+///
+/// Generally, it is inferred from [`serde_json::Value::Number`]s at the same position when number
+/// ranges are tracked, so that generators may pick a specific type (e.g. `u32` in Rust) or
+/// constraints for it. O.W., numbers are just [`Int`](super::Type::Int) or
+/// [`Float`](super::Type::Float).
+///
+/// Bounds are kept as `f64`, which is exact for all integers within `±2^53`.
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub struct Number {
+    /// Whether every value is written as an integer (e.g. `1` instead of `1.0`), in which case
+    /// it is an integer type
+    pub is_integer: bool,
+    /// Whether every value is a whole number, even if some are written as floats (e.g. `1.0`)
+    pub is_whole: bool,
+    /// The smallest value observed
+    pub min: f64,
+    /// The largest value observed
+    pub max: f64,
+}
+
+impl Number {
+    /// Create a number type with only a value observed
+    pub fn new(value: f64, is_integer: bool) -> Self {
+        Number {
+            is_integer,
+            is_whole: value.fract() == 0.0,
+            min: value,
+            max: value,
+        }
+    }
+
+    /// Merge the observations of another number type at the same position
+    pub fn union(&self, other: &Number) -> Number {
+        Number {
+            is_integer: self.is_integer && other.is_integer,
+            is_whole: self.is_whole && other.is_whole,
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+
+    /// Check if no value observed is negative
+    pub fn is_non_negative(&self) -> bool {
+        self.min >= 0.0
+    }
+
+    /// Check if all values observed are integers that are exactly representable in JavaScript
+    pub fn is_safe_integer(&self) -> bool {
+        self.is_whole && -MAX_SAFE_INTEGER <= self.min && self.max <= MAX_SAFE_INTEGER
+    }
+}
@@ -1,23 +1,64 @@
+use indexmap::IndexSet;
+
 use std::{
     collections::HashSet,
     fmt::{self, Display},
 };
 
-use super::{arena::ArenaIndex, name_hints::NameHints};
+use super::{
+    arena::{ArenaIndex, ITypeArena},
+    map::Map,
+    name_hints::NameHints,
+    Type,
+};
 
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Union {
     pub name_hints: NameHints,
-    pub types: HashSet<ArenaIndex>,
+    /// The unique name resolved from `name_hints` by [`Schema::resolve_names`](super::Schema::resolve_names)
+    pub name: Option<String>,
+    /// Members of the union, in the order they are first seen
+    pub types: IndexSet<ArenaIndex>,
+}
+
+impl Union {
+    /// Get the key of the field that tells apart all maps in the union by constant strings, if it
+    /// is a tagged union
+    ///
+    /// Such a field is an enum of a single variant in every map, with no two maps sharing a value.
+    /// Other than maps, there may be only `Null` or `Missing` in the union.
+    pub fn discriminator<'a>(&self, arena: &'a impl ITypeArena) -> Option<&'a str> {
+        let maps = self
+            .types
+            .iter()
+            .map(|&arni| arena.get(arni).unwrap())
+            .filter(|r#type| !r#type.is_null() && !r#type.is_missing())
+            .map(Type::as_map)
+            .collect::<Option<Vec<&Map>>>()?;
+        if maps.len() < 2 {
+            return None;
+        }
+        maps[0]
+            .fields
+            .keys()
+            .find(|key| {
+                let mut seen = HashSet::new();
+                maps.iter().all(|map| {
+                    map.tag(key, arena)
+                        .is_some_and(|(value, _)| seen.insert(value))
+                })
+            })
+            .map(String::as_str)
+    }
 }
 
 impl Display for Union {
+    /// Display the unique name if resolved, or the name hints otherwise
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        if self.name_hints.is_empty() {
-            // NOTE: the type wrapper of map should be stored in Arena for persistent memory address.
-            write!(f, "UnnammedUnion{:X}", self as *const Union as usize)
-        } else {
-            self.name_hints.fmt(f)
+        match self.name {
+            Some(ref name) => name.fmt(f),
+            None if self.name_hints.is_empty() => write!(f, "UnnamedUnion"),
+            None => self.name_hints.fmt(f),
         }
     }
 }
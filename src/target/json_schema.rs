@@ -0,0 +1,206 @@
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, ser::PrettyFormatter, Map as JSONMap, Serializer, Value as JSONValue};
+
+use std::fmt::{self, Write};
+
+use crate::schema::{ArenaIndex, ITypeArena, Primitive, Schema, Type};
+
+use super::{Indentation, TargetGenerator};
+
+/// Generator for a [JSON Schema](https://json-schema.org/) (2020-12) document
+///
+/// Dominant types (i.e. those referenced more than once, including recursive ones) are placed in
+/// `$defs` and referenced by `$ref`, while others are inlined.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct JSONSchema {
+    pub indentation: Indentation,
+}
+
+impl TargetGenerator for JSONSchema {
+    fn write_output(
+        &self,
+        schema: &Schema,
+        header: &mut dyn Write,
+        body: &mut dyn Write,
+        additional: &mut dyn Write,
+    ) -> fmt::Result {
+        write_output(schema, self, header, body, additional)
+    }
+}
+
+#[inline(always)]
+fn write_output(
+    schema: &Schema,
+    options: &JSONSchema,
+    _header: &mut dyn Write,
+    body: &mut dyn Write,
+    _additional: &mut dyn Write,
+) -> fmt::Result {
+    // Only named types are worth a definition. Primitive types are always shared in the arena.
+    let defs: IndexMap<ArenaIndex, String> = schema
+        .get_dominant()
+        .into_iter()
+        .filter_map(|arni| {
+            let name = match *schema.arena.get(arni).unwrap() {
+                Type::Map(ref map) => map.to_string(),
+                Type::Union(ref union) => union.to_string(),
+                Type::Enum(ref r#enum) => r#enum.to_string(),
+                _ => return None,
+            };
+            Some((arni, name))
+        })
+        .collect();
+    let converter = Converter {
+        schema,
+        defs: &defs,
+    };
+
+    let mut document = JSONMap::new();
+    document.insert(
+        String::from("$schema"),
+        json!("https://json-schema.org/draft/2020-12/schema"),
+    );
+    match converter.convert(schema.root) {
+        JSONValue::Object(root) => document.extend(root),
+        _ => unreachable!("A type is always converted to an object"),
+    }
+    if !defs.is_empty() {
+        document.insert(
+            String::from("$defs"),
+            defs.iter()
+                .map(|(&arni, name)| (name.to_owned(), converter.convert_def(arni)))
+                .collect::<JSONMap<_, _>>()
+                .into(),
+        );
+    }
+
+    let indentation = options.indentation.to_string();
+    let mut buffer = Vec::new();
+    let mut serializer = Serializer::with_formatter(
+        &mut buffer,
+        PrettyFormatter::with_indent(indentation.as_bytes()),
+    );
+    JSONValue::Object(document)
+        .serialize(&mut serializer)
+        .map_err(|_| fmt::Error)?;
+    writeln!(
+        body,
+        "{}",
+        String::from_utf8(buffer).expect("JSON is always valid UTF-8")
+    )
+}
+
+struct Converter<'c> {
+    schema: &'c Schema,
+    /// Types placed in `$defs`, with their names
+    defs: &'c IndexMap<ArenaIndex, String>,
+}
+
+impl<'c> Converter<'c> {
+    /// Convert a type into a subschema, or a `$ref` to it if it is placed in `$defs`
+    fn convert(&self, arni: ArenaIndex) -> JSONValue {
+        match self.defs.get(&arni) {
+            Some(name) => json!({ "$ref": format!("#/$defs/{}", name) }),
+            None => self.convert_def(arni),
+        }
+    }
+
+    /// Convert a type into a subschema, always inlined
+    fn convert_def(&self, arni: ArenaIndex) -> JSONValue {
+        let schema = self.schema;
+        match *schema.arena.get(arni).unwrap() {
+            Type::Map(ref map) => {
+                let the_missing = schema.arena.get_index_of_primitive(Primitive::Missing);
+                let mut properties = JSONMap::new();
+                let mut required = vec![];
+                for (key, &r#type) in map.fields.iter() {
+                    let optional = match *schema.arena.get(r#type).unwrap() {
+                        Type::Union(ref union) => union.types.contains(&the_missing),
+                        Type::Missing => true,
+                        _ => false,
+                    };
+                    if !optional {
+                        required.push(JSONValue::from(key.as_str()));
+                    }
+                    properties.insert(key.to_owned(), self.convert(r#type));
+                }
+                let mut object = JSONMap::new();
+                if !map.name_hints.is_empty() {
+                    object.insert(String::from("title"), map.to_string().into());
+                }
+                object.insert(String::from("type"), json!("object"));
+                object.insert(String::from("properties"), properties.into());
+                if !required.is_empty() {
+                    object.insert(String::from("required"), required.into());
+                }
+                object.into()
+            }
+            Type::Union(ref union) => {
+                let the_missing = schema.arena.get_index_of_primitive(Primitive::Missing);
+                // Missing is expressed by the absence in `required` of the outer object.
+                let mut types: Vec<JSONValue> = union
+                    .types
+                    .iter()
+                    .filter(|&&r#type| r#type != the_missing)
+                    .map(|&r#type| self.convert(r#type))
+                    .collect();
+                match types.len() {
+                    0 => json!({}),
+                    1 => types.pop().unwrap(),
+                    _ => json!({ "anyOf": types }),
+                }
+            }
+            Type::Enum(ref r#enum) => {
+                let mut object = JSONMap::new();
+                if !r#enum.name_hints.is_empty() {
+                    object.insert(String::from("title"), r#enum.to_string().into());
+                }
+                object.insert(String::from("type"), json!("string"));
+                object.insert(
+                    String::from("enum"),
+                    r#enum.variants.keys().cloned().collect::<Vec<_>>().into(),
+                );
+                object.into()
+            }
+            Type::Array(inner) => match *schema.arena.get(inner).unwrap() {
+                Type::Any => json!({ "type": "array" }),
+                _ => json!({ "type": "array", "items": self.convert(inner) }),
+            },
+            Type::Tuple(ref types) => json!({
+                "type": "array",
+                "prefixItems": types.iter().map(|&r#type| self.convert(r#type)).collect::<Vec<_>>(),
+                "items": false,
+            }),
+            // Keys of JSON objects are always strings, regardless of what they look like.
+            Type::Dict(_, value) => {
+                json!({ "type": "object", "additionalProperties": self.convert(value) })
+            }
+            Type::Int => json!({ "type": "integer" }),
+            Type::Float => json!({ "type": "number" }),
+            Type::Number(ref number) if number.is_integer => json!({ "type": "integer" }),
+            Type::Number(_) => json!({ "type": "number" }),
+            Type::Bool => json!({ "type": "boolean" }),
+            Type::String => json!({ "type": "string" }),
+            Type::Date => json!({ "type": "string", "format": "date-time" }),
+            Type::UUID => json!({ "type": "string", "format": "uuid" }),
+            Type::PlainDate => json!({ "type": "string", "format": "date" }),
+            Type::Time => json!({ "type": "string", "format": "time" }),
+            Type::Duration => json!({ "type": "string", "format": "duration" }),
+            Type::URL => json!({ "type": "string", "format": "uri" }),
+            Type::Email => json!({ "type": "string", "format": "email" }),
+            // There is no single format for both IPv4 and IPv6.
+            Type::IPAddress => json!({
+                "anyOf": [
+                    { "type": "string", "format": "ipv4" },
+                    { "type": "string", "format": "ipv6" },
+                ],
+            }),
+            // Not a format defined by the specification, but one of the common extensions
+            Type::Decimal => json!({ "type": "string", "format": "decimal" }),
+            Type::Bytes => json!({ "type": "string", "contentEncoding": "base64" }),
+            Type::Null => json!({ "type": "null" }),
+            Type::Missing | Type::Any => json!({}),
+        }
+    }
+}
@@ -0,0 +1,440 @@
+use serde::{Deserialize, Serialize};
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet, VecDeque},
+    fmt::{self, Display, Write},
+    str::FromStr,
+};
+
+use crate::error::Result;
+use crate::schema::{ArenaIndex, ITypeArena, Schema, Type};
+
+mod python_class;
+pub use python_class::{Kind as PythonKind, PythonClass};
+mod rust;
+pub use rust::RustSerde;
+mod python_inline;
+pub use python_inline::PythonTypedDict;
+mod typescript;
+pub use typescript::TypeScriptInterface;
+mod json_schema;
+pub use json_schema::JSONSchema;
+
+// pub use dataclasses::*;
+
+// pub enum TargetLang {
+//     PythonDataclasses(dataclasses::Options),
+//     PythonTypedDict,
+//     PythonPydantic,
+//     RustSerde,
+//     TypeScriptInterface
+// }
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GenOutput {
+    pub header: String,
+    pub body: String,
+    pub additional: String,
+}
+
+impl Display for GenOutput {
+    /// Concatenate all non-empty parts of the output, separated by a blank line
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let parts = [&self.header, &self.body, &self.additional];
+        let mut parts = parts.iter().filter(|s| !s.is_empty()).peekable();
+        while let Some(part) = parts.next() {
+            write!(f, "{}", part)?;
+            if parts.peek().is_some() {
+                writeln!(f)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// #[typetag::serde(tag = "target")]
+// pub trait TargetGenerator {
+//     fn generate();
+// }
+
+// #[typetag::serde(tag = "target")]
+pub trait TargetGenerator {
+    fn generate(&self, schema: &Schema) -> Result<GenOutput> {
+        // Types are referred to by their unique names, which are resolved on a copy of the schema
+        // to leave it as is.
+        let mut schema = schema.clone();
+        schema.resolve_names(self.reserved_names());
+        let schema = &schema;
+        let mut header = String::new();
+        let mut body = String::new();
+        let mut additional = String::new();
+        self.write_output(schema, &mut header, &mut body, &mut additional)?;
+        fix_redundant_trailing_line_feed(&mut header);
+        fix_redundant_trailing_line_feed(&mut body);
+        fix_redundant_trailing_line_feed(&mut additional);
+        Ok(GenOutput {
+            header,
+            body,
+            additional,
+        })
+    }
+
+    /// Names that types are never given, as they are taken by imports or builtins in the output
+    fn reserved_names(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    fn write_output(
+        &self,
+        schema: &Schema,
+        header: &mut dyn Write,
+        body: &mut dyn Write,
+        additional: &mut dyn Write,
+    ) -> fmt::Result;
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub enum Indentation {
+    Space(usize),
+    Tab,
+}
+
+impl Display for Indentation {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Indentation::Space(len) => {
+                for _ in 0..len {
+                    write!(fmt, " ")?;
+                }
+            }
+            Indentation::Tab => {
+                write!(fmt, "\t")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub enum Quote {
+    Single,
+    Double,
+}
+
+impl Display for Quote {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Quote::Single => r#"'"#,
+            Quote::Double => r#"""#,
+        }
+        .fmt(f)
+    }
+}
+
+impl Quote {
+    fn as_char(&self) -> char {
+        match *self {
+            Quote::Single => '\'',
+            Quote::Double => '"',
+        }
+    }
+}
+
+/// The order in which type definitions are generated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Order {
+    /// Every type is defined before it is referenced, so that no forward reference is needed
+    /// except in cycles of recursive types
+    DependenciesFirst,
+    /// The root type comes first, followed by the types it references level by level
+    RootFirst,
+    /// Types are sorted by their names
+    Alphabetical,
+    /// Types are in the order they are first seen when walking down from the root
+    Source,
+}
+
+/// Get all types reachable from the root of the schema in the given order
+fn ordered(schema: &Schema, order: Order) -> Vec<ArenaIndex> {
+    match order {
+        Order::DependenciesFirst => {
+            // Post-order DFS, i.e. a topological sort with back edges of cycles ignored
+            fn visit(
+                schema: &Schema,
+                arni: ArenaIndex,
+                seen: &mut HashSet<ArenaIndex>,
+                types: &mut Vec<ArenaIndex>,
+            ) {
+                if seen.insert(arni) {
+                    for inner in schema.arena.get(arni).unwrap().inner_types() {
+                        visit(schema, inner, seen, types);
+                    }
+                    types.push(arni);
+                }
+            }
+            let mut types = vec![];
+            visit(schema, schema.root, &mut HashSet::new(), &mut types);
+            types
+        }
+        Order::RootFirst => {
+            // BFS
+            let mut types = vec![schema.root];
+            let mut seen: HashSet<ArenaIndex> = types.iter().cloned().collect();
+            let mut queue: VecDeque<ArenaIndex> = types.iter().cloned().collect();
+            while let Some(arni) = queue.pop_front() {
+                for inner in schema.arena.get(arni).unwrap().inner_types() {
+                    if seen.insert(inner) {
+                        types.push(inner);
+                        queue.push_back(inner);
+                    }
+                }
+            }
+            types
+        }
+        Order::Alphabetical => {
+            let mut types: Vec<ArenaIndex> = schema.iter_topdown().collect();
+            // Anonymous types are left in front as they are not to be defined.
+            types.sort_by_cached_key(|&arni| match *schema.arena.get(arni).unwrap() {
+                Type::Map(ref map) => Some(map.to_string()),
+                Type::Union(ref union) => Some(union.to_string()),
+                Type::Enum(ref r#enum) => Some(r#enum.to_string()),
+                _ => None,
+            });
+            types
+        }
+        Order::Source => schema.iter_topdown().collect(),
+    }
+}
+
+/// The minimum Python version that generated code is expected to run on, which determines the
+/// syntax and the imports to use
+///
+/// As `from __future__ import annotations` is always used, Python 3.7 is the earliest supported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct PythonVersion(pub u8, pub u8);
+
+impl PythonVersion {
+    /// Check if `X | Y` is supported, as in [PEP 604](https://peps.python.org/pep-0604/)
+    fn has_union_operator(self) -> bool {
+        self >= PythonVersion(3, 10)
+    }
+
+    /// Check if `list[T]` and the like are supported, as in [PEP 585](https://peps.python.org/pep-0585/)
+    fn has_builtin_generics(self) -> bool {
+        self >= PythonVersion(3, 9)
+    }
+
+    /// Check if `dataclass` supports `kw_only`
+    fn has_kw_only(self) -> bool {
+        self >= PythonVersion(3, 10)
+    }
+
+    /// Check if a name is yet to be added to `typing`, requiring `typing_extensions` instead
+    fn lacks_in_typing(self, name: &str) -> bool {
+        match name {
+            "TypedDict" | "Literal" => self < PythonVersion(3, 8),
+            "Annotated" => self < PythonVersion(3, 9),
+            // PEP 655
+            "NotRequired" => self < PythonVersion(3, 11),
+            _ => false,
+        }
+    }
+}
+
+impl Display for PythonVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}", self.0, self.1)
+    }
+}
+
+impl FromStr for PythonVersion {
+    type Err = String;
+
+    /// Parse a version like `3.10`
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let invalid = || format!("invalid Python version `{}`, expected one like `3.10`", s);
+        let (major, minor) = s.split_once('.').ok_or_else(invalid)?;
+        let version = PythonVersion(
+            major.parse().map_err(|_| invalid())?,
+            minor.parse().map_err(|_| invalid())?,
+        );
+        if version < PythonVersion(3, 7) {
+            return Err(format!(
+                "Python {} is not supported, 3.7 or later is required",
+                s
+            ));
+        }
+        Ok(version)
+    }
+}
+
+/// Write imports of names from `typing`, or from `typing_extensions` for those not yet available
+/// in the target Python version, along with hints for newer syntax into `additional`
+fn write_typing_imports(
+    header: &mut dyn Write,
+    additional: &mut dyn Write,
+    names: &BTreeSet<&str>,
+    version: PythonVersion,
+) -> fmt::Result {
+    if names.contains("Union") && !version.has_union_operator() {
+        writeln!(additional, "# 💡 Starting from Python 3.10 (PEP 604), `Union[A, B]` can be simplified as `A | B`\n")?;
+    }
+    let (extensions, typing): (Vec<&str>, Vec<&str>) = names
+        .iter()
+        .partition(|&&name| version.lacks_in_typing(name));
+    if !typing.is_empty() {
+        writeln!(header, "from typing import {}", typing.join(", "))?;
+    }
+    if !extensions.is_empty() {
+        writeln!(
+            header,
+            "from typing_extensions import {}",
+            extensions.join(", ")
+        )?;
+        writeln!(
+            additional,
+            "# 💡 `{}` {} not in `typing` as of Python {}.
+#    `typing_extensions` is imported above for backwards compatibility. pip install typing_extensions\n",
+            extensions.join("`, `"),
+            if extensions.len() > 1 { "are" } else { "is" },
+            version
+        )?;
+    }
+    Ok(())
+}
+
+/// Names that generated Python code may import (e.g. `field` from `dataclasses`) or use as builtins
+/// (e.g. `int`), which must not be shadowed by names of fields or classes
+const PYTHON_NAMES: &[&str] = &[
+    "Annotated",
+    "Any",
+    "AnyUrl",
+    "BaseModel",
+    "ConfigDict",
+    "Decimal",
+    "Dict",
+    "EmailStr",
+    "Enum",
+    "False",
+    "Field",
+    "IPvAnyAddress",
+    "List",
+    "Literal",
+    "None",
+    "NotRequired",
+    "Optional",
+    "True",
+    "Tuple",
+    "TypedDict",
+    "UUID",
+    "Union",
+    "bool",
+    "bytes",
+    "config",
+    "dataclass",
+    "dataclass_json",
+    "date",
+    "datetime",
+    "dict",
+    "field",
+    "float",
+    "int",
+    "list",
+    "str",
+    "time",
+    "timedelta",
+    "tuple",
+];
+
+/// Get the Python type of a string with a specific format (e.g. `Date`), along with the module to
+/// import it from if any
+///
+/// Formats without a counterpart in the standard library are validated by `pydantic` types if
+/// `is_pydantic`, or left as `str` otherwise.
+fn python_formatted_string(
+    r#type: &Type,
+    is_pydantic: bool,
+) -> Option<(Option<&'static str>, &'static str)> {
+    Some(match *r#type {
+        Type::Date => (Some("datetime"), "datetime"),
+        Type::PlainDate => (Some("datetime"), "date"),
+        Type::Time => (Some("datetime"), "time"),
+        Type::Duration => (Some("datetime"), "timedelta"),
+        Type::UUID => (Some("uuid"), "UUID"),
+        Type::Decimal => (Some("decimal"), "Decimal"),
+        Type::Bytes => (None, "bytes"),
+        Type::URL if is_pydantic => (Some("pydantic"), "AnyUrl"),
+        Type::Email if is_pydantic => (Some("pydantic"), "EmailStr"),
+        Type::IPAddress if is_pydantic => (Some("pydantic"), "IPvAnyAddress"),
+        Type::URL | Type::Email | Type::IPAddress => (None, "str"),
+        _ => return None,
+    })
+}
+
+/// Write imports of names from modules other than `typing`, one line per module
+fn write_module_imports(
+    header: &mut dyn Write,
+    imports: &BTreeMap<&str, BTreeSet<&str>>,
+) -> fmt::Result {
+    for (module, names) in imports.iter() {
+        let names: Vec<&str> = names.iter().copied().collect();
+        writeln!(header, "from {} import {}", module, names.join(", "))?;
+    }
+    Ok(())
+}
+
+/// A string literal that is quoted and escaped as in Python when displayed
+struct Quoted<'a>(&'a str, &'a Quote);
+
+impl<'a> Display for Quoted<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let &Quoted(s, quote) = self;
+        let quote = quote.as_char();
+        write!(f, "{}", quote)?;
+        for c in s.chars() {
+            match c {
+                '\\' => write!(f, "\\\\")?,
+                '\n' => write!(f, "\\n")?,
+                '\r' => write!(f, "\\r")?,
+                '\t' => write!(f, "\\t")?,
+                c if c == quote => write!(f, "\\{}", c)?,
+                c => write!(f, "{}", c)?,
+            }
+        }
+        write!(f, "{}", quote)
+    }
+}
+
+/// A helper type that facilitate taking advantage of [`Display`](std::fmt::Display)
+struct Contexted<I, C: Copy> {
+    inner: I,
+    context: C,
+}
+
+impl<I, C: Copy> Contexted<I, C> {
+    /// Wrap another type using the schema and the generator options of the current wrapper
+    fn wrap<OtherI>(&self, another: OtherI) -> Contexted<OtherI, C> {
+        with_context(another, self.context)
+    }
+}
+
+/// Create and return a new [`WrappedType`]
+fn with_context<I, C: Copy>(inner: I, context: C) -> Contexted<I, C> {
+    Contexted { inner, context }
+}
+
+// trait IContext<C> {
+//     fn with<I>(self, inner: I) -> Contexted<I, Self> {
+//         with_context(inner, self)
+//     }
+// }
+
+fn fix_redundant_trailing_line_feed(s: &mut String) {
+    // TODO: ensure write_ouput returns no redundant \n
+    while s.ends_with('\n') {
+        s.pop().unwrap();
+    }
+    if !s.is_empty() {
+        s.push('\n');
+    }
+}
@@ -0,0 +1,951 @@
+use indexmap::{IndexMap, IndexSet};
+use inflector::Inflector;
+use itertools::{multipeek, Itertools};
+use serde::{Deserialize, Serialize};
+
+use crate::schema::{ArenaIndex, Enum, ITypeArena, Map, Primitive, Schema, Type, Union};
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet, HashSet},
+    fmt::{self, Display, Write},
+};
+
+use super::{
+    ordered, python_formatted_string, with_context, write_module_imports, write_typing_imports,
+    Contexted, Indentation, Order, PythonVersion, Quote, Quoted, TargetGenerator, PYTHON_NAMES,
+};
+
+/// The context for displaying types, with names that have been defined so far if the type is
+/// evaluated eagerly (i.e. not in annotations which are postponed), and names to import from
+/// `typing` as they are used
+#[derive(Clone, Copy, Debug)]
+struct Context<'c>(
+    &'c Schema,
+    &'c PythonClass,
+    Option<&'c HashSet<String>>,
+    &'c RefCell<BTreeSet<&'static str>>,
+);
+
+impl<'c> Context<'c> {
+    fn is_defined(&self, name: &str) -> bool {
+        self.2.is_none_or(|defined| defined.contains(name))
+    }
+
+    /// Format a reference to a type definition, which is quoted as a forward reference if it is
+    /// evaluated eagerly before being defined
+    fn reference(&self, name: String) -> String {
+        if self.is_defined(&name) {
+            name
+        } else {
+            format!("\"{}\"", name)
+        }
+    }
+
+    /// Check if a type is referenced by a quoted forward reference, which does not support `|`
+    fn is_forward_reference(&self, r#type: &Type) -> bool {
+        match *r#type {
+            Type::Map(ref map) => !self.is_defined(&map.to_string()),
+            Type::Enum(ref r#enum) => {
+                !self.is_literal(r#enum) && !self.is_defined(&r#enum.to_string())
+            }
+            Type::Union(ref union) => {
+                self.1.to_generate_type_alias_for_union
+                    && is_non_trivial(self.0, &union.types)
+                    && !self.is_defined(&union.to_string())
+            }
+            _ => false,
+        }
+    }
+
+    /// Use a name from `typing`, which is imported accordingly
+    fn import(&self, name: &'static str) -> &'static str {
+        self.3.borrow_mut().insert(name);
+        name
+    }
+
+    /// Check if an enum is expressed as a `Literal` instead of a class, as a constant (e.g. the
+    /// tag of a variant of a tagged union)
+    fn is_literal(&self, r#enum: &Enum) -> bool {
+        r#enum.variants.len() == 1
+    }
+
+    /// Check if a union is an array that may be absent, which defaults to an empty list
+    fn is_absent_array(&self, types: &IndexSet<ArenaIndex>) -> bool {
+        let schema = self.0;
+        self.1.kind.has_default_factories()
+            && types.len() == 2
+            && types.contains(&schema.arena.get_index_of_primitive(Primitive::Missing))
+            && types
+                .iter()
+                .any(|&arni| schema.arena.get(arni).unwrap().is_array())
+    }
+
+    /// Get inner types of a union, with `Missing` taken as `Null` (or dropped for arrays that
+    /// default to empty lists) unless `NotRequired` is used for it
+    fn members<'s>(&self, types: &'s IndexSet<ArenaIndex>) -> Cow<'s, IndexSet<ArenaIndex>> {
+        let missing = self.0.arena.get_index_of_primitive(Primitive::Missing);
+        if self.1.kind == Kind::TypedDict || !types.contains(&missing) {
+            Cow::Borrowed(types)
+        } else if self.is_absent_array(types) {
+            Cow::Owned(
+                types
+                    .iter()
+                    .cloned()
+                    .filter(|&arni| arni != missing)
+                    .collect(),
+            )
+        } else {
+            let null = self.0.arena.get_index_of_primitive(Primitive::Null);
+            Cow::Owned(
+                types
+                    .iter()
+                    .map(|&arni| if arni == missing { null } else { arni })
+                    .collect(),
+            )
+        }
+    }
+
+    /// Get the default value of a field of the type, if it may be absent or `null`
+    fn field_default(&self, r#type: &Type) -> Option<FieldDefault> {
+        let null = self.0.arena.get_index_of_primitive(Primitive::Null);
+        if !self.1.kind.has_defaults() {
+            return None;
+        }
+        match *r#type {
+            Type::Null | Type::Missing => Some(FieldDefault::None),
+            Type::Union(ref union) if self.is_absent_array(&union.types) => {
+                Some(FieldDefault::EmptyList)
+            }
+            Type::Union(ref union) if self.members(&union.types).contains(&null) => {
+                Some(FieldDefault::None)
+            }
+            _ => None,
+        }
+    }
+
+    /// Get the name of the field that tells apart maps in a tagged union, if it is annotated as
+    /// the discriminator
+    fn discriminator(&self, union: &Union) -> Option<String> {
+        if !self.1.kind.has_discriminators() {
+            return None;
+        }
+        let Context(schema, options, _, _) = *self;
+        let key = union.discriminator(&schema.arena)?;
+        let map = union
+            .types
+            .iter()
+            .find_map(|&arni| schema.arena.get(arni).unwrap().as_map())
+            .unwrap();
+        let index = map.fields.get_index_of(key).unwrap();
+        Some(field_names(map, &options.kind).swap_remove(index))
+    }
+}
+
+/// Check if a type can reach itself through its inner types
+fn is_recursive(schema: &Schema, arni: ArenaIndex) -> bool {
+    let mut seen = HashSet::new();
+    let mut stack = schema.arena.get(arni).unwrap().inner_types();
+    while let Some(inner) = stack.pop() {
+        if inner == arni {
+            return true;
+        }
+        if seen.insert(inner) {
+            stack.extend(schema.arena.get(inner).unwrap().inner_types());
+        }
+    }
+    false
+}
+
+/// Check if a union has at least two inner types other than `Null` and `Missing`
+fn is_non_trivial(schema: &Schema, types: &IndexSet<ArenaIndex>) -> bool {
+    (types.len()
+        - types.contains(&schema.arena.get_index_of_primitive(Primitive::Null)) as usize
+        - types.contains(&schema.arena.get_index_of_primitive(Primitive::Missing)) as usize)
+        > 1
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PythonClass {
+    pub kind: Kind,
+    pub to_generate_type_alias_for_union: bool,
+    // pub use_pydantic_datamodel: bool,
+    pub indentation: Indentation,
+    pub order: Order,
+    pub python_version: PythonVersion,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+/// Sub-target for Python type definitions generator
+pub enum Kind {
+    /// Use `dataclass` from built-in `dataclasses` module as the decorator
+    Dataclass,
+    /// Use `dataclass` from built-in `dataclasses` module as the decorator, additionally
+    /// decorating with the external library `dataclass-json` for JSON (de)serilization support
+    DataclassWithJSON,
+    /// Use `BaseModel` from the external data validation framework [`pydantic`](https://pydantic-docs.helpmanual.io/)
+    /// as the base class
+    PydanticBaseModel,
+    /// Use [`dataclass` from pydantic](https://pydantic-docs.helpmanual.io/usage/dataclasses/) as
+    /// the decorator
+    PydanticDataclass,
+    /// Use `BaseModel` from [`pydantic` v2](https://docs.pydantic.dev/latest/concepts/models/) as
+    /// the base class, with defaults for optional fields and `Literal`s for discriminators
+    PydanticV2BaseModel,
+    /// Use `TypedDict` from the built-in `typing` module as the base class, as explained in [PEP-589](https://www.python.org/dev/peps/pep-0589/#class-based-syntax)
+    TypedDict, // TODO: totality?
+}
+
+impl Kind {
+    /// Check if fields that may be absent or `null` are given `None` as defaults
+    fn has_defaults(&self) -> bool {
+        matches!(
+            *self,
+            Kind::Dataclass | Kind::DataclassWithJSON | Kind::PydanticV2BaseModel
+        )
+    }
+
+    /// Check if arrays that may be absent default to empty lists instead of `None`
+    fn has_default_factories(&self) -> bool {
+        matches!(*self, Kind::Dataclass | Kind::DataclassWithJSON)
+    }
+
+    /// Check if fields with defaults must not precede those without, as in `__init__` generated
+    /// by `dataclass`
+    fn has_positional_fields(&self) -> bool {
+        matches!(
+            *self,
+            Kind::Dataclass | Kind::DataclassWithJSON | Kind::PydanticDataclass
+        )
+    }
+
+    /// Check if aliases are given as defaults of fields (i.e. `Field(alias=...)`), which makes
+    /// aliased fields count as those with defaults in `__init__`
+    fn has_aliases_as_defaults(&self) -> bool {
+        matches!(*self, Kind::PydanticDataclass)
+    }
+
+    /// Check if tagged unions are annotated with their discriminators
+    fn has_discriminators(&self) -> bool {
+        matches!(*self, Kind::PydanticV2BaseModel)
+    }
+
+    /// Check if a field name is reserved by the base class, e.g. `model_config` or `model_dump`
+    /// in the protected namespace of `pydantic` v2
+    fn is_reserved(&self, name: &str) -> bool {
+        match *self {
+            Kind::PydanticV2BaseModel => name.starts_with("model_"),
+            _ => false,
+        }
+    }
+
+    /// Check if classes are validated by `pydantic`, whose types can be used for fields
+    fn is_pydantic(&self) -> bool {
+        matches!(
+            *self,
+            Kind::PydanticBaseModel | Kind::PydanticDataclass | Kind::PydanticV2BaseModel
+        )
+    }
+}
+
+/// The default value of a field that may be absent or `null`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FieldDefault {
+    None,
+    EmptyList,
+}
+
+impl FieldDefault {
+    /// Get the keyword argument to `field` or `Field` that specifies the default
+    fn as_argument(&self) -> &'static str {
+        match *self {
+            FieldDefault::None => "default=None",
+            FieldDefault::EmptyList => "default_factory=list",
+        }
+    }
+}
+
+// #[typetag::serde]
+impl TargetGenerator for PythonClass {
+    fn reserved_names(&self) -> &'static [&'static str] {
+        PYTHON_NAMES
+    }
+
+    fn write_output(
+        &self,
+        schema: &Schema,
+        header: &mut dyn Write,
+        body: &mut dyn Write,
+        additional: &mut dyn Write,
+    ) -> fmt::Result {
+        write_output(schema, self, header, body, additional)
+    }
+}
+
+#[inline(always)]
+fn write_output(
+    schema: &Schema,
+    options: &PythonClass,
+    header: &mut dyn Write,
+    body: &mut dyn Write,
+    additional: &mut dyn Write,
+) -> fmt::Result {
+    // Sorted, so that the output is deterministic
+    let imports_from_typing = RefCell::new(BTreeSet::new());
+    let wrapper = with_context((), Context(schema, options, None, &imports_from_typing)); // helper
+
+    let decorators = match options.kind {
+        Kind::Dataclass | Kind::PydanticDataclass => "@dataclass\n",
+        Kind::DataclassWithJSON => "@dataclass_json\n@dataclass\n",
+        _ => "",
+    };
+    let base_class = match options.kind {
+        Kind::PydanticBaseModel | Kind::PydanticV2BaseModel => "(BaseModel)",
+        Kind::TypedDict => "(TypedDict)",
+        _ => "",
+    };
+
+    let mut importing_base_class_or_class_decorators = false;
+    // e.g. `datetime` from `datetime`, sorted so that the output is deterministic
+    let mut imports_from_modules = BTreeMap::<&str, BTreeSet<&str>>::new();
+    let mut importing_enum = false;
+    let mut importing_field = false;
+    let mut importing_config_dict = false;
+    // Recursive pydantic models are to be rebuilt after all referenced types are defined.
+    let mut to_rebuild = vec![];
+    // `from __future__ import annotations` postpones the evaluation of annotations. But type
+    // aliases and functional TypedDicts are evaluated eagerly, where references to types not yet
+    // defined need quoting.
+    let mut defined = HashSet::<String>::new();
+
+    for arni in ordered(schema, options.order) {
+        let r#type = schema.arena.get(arni).unwrap();
+        // imports for types that may appear anywhere, not only as fields
+        if let Some((Some(module), name)) =
+            python_formatted_string(r#type, options.kind.is_pydantic())
+        {
+            imports_from_modules.entry(module).or_default().insert(name);
+        }
+        match *r#type {
+            Type::Union(ref union) if wrapper.context.discriminator(union).is_some() => {
+                importing_field = true;
+            }
+            _ => {}
+        }
+        match *r#type {
+            Type::Map(ref map @ Map { ref fields, .. }) => {
+                importing_base_class_or_class_decorators = true;
+                let names = field_names(map, &options.kind);
+                let aliased = names
+                    .iter()
+                    .zip(fields.keys())
+                    .any(|(name, key)| name != key);
+                if aliased && options.kind == Kind::TypedDict {
+                    // Keys that are not valid identifiers can only be expressed in the
+                    // alternative functional syntax.
+                    writeln!(
+                        body,
+                        "{} = TypedDict({}, {})",
+                        map,
+                        Quoted(&map.to_string(), &Quote::Double),
+                        with_context(
+                            fields,
+                            Context(schema, options, Some(&defined), &imports_from_typing)
+                        )
+                    )?;
+                } else {
+                    let defaults: Vec<Option<FieldDefault>> = fields
+                        .values()
+                        .map(|&arni| {
+                            wrapper
+                                .context
+                                .field_default(schema.arena.get(arni).unwrap())
+                        })
+                        .collect();
+                    importing_field |= aliased || defaults.contains(&Some(FieldDefault::EmptyList));
+                    // A field without default following one with default is not allowed in
+                    // `__init__`. So either make fields keyword-only or move them forward.
+                    let are_defaulted: Vec<bool> = defaults
+                        .iter()
+                        .zip(names.iter().zip(fields.keys()))
+                        .map(|(default, (name, key))| {
+                            default.is_some()
+                                || (options.kind.has_aliases_as_defaults() && name != key)
+                        })
+                        .collect();
+                    let is_misordered = options.kind.has_positional_fields()
+                        && are_defaulted
+                            .iter()
+                            .skip_while(|&&is_defaulted| !is_defaulted)
+                            .any(|&is_defaulted| !is_defaulted);
+                    let kw_only = is_misordered && options.python_version.has_kw_only();
+                    let decorators = match options.kind {
+                        Kind::Dataclass if kw_only => "@dataclass(kw_only=True)\n",
+                        Kind::DataclassWithJSON if kw_only => {
+                            "@dataclass_json\n@dataclass(kw_only=True)\n"
+                        }
+                        Kind::PydanticDataclass if kw_only => "@dataclass(kw_only=True)\n",
+                        _ => decorators,
+                    };
+                    let (fields, names) = if is_misordered && !kw_only {
+                        let (required, optional): (Vec<_>, Vec<_>) = fields
+                            .iter()
+                            .zip(names)
+                            .zip(are_defaulted)
+                            .partition(|&(_, is_defaulted)| !is_defaulted);
+                        required
+                            .into_iter()
+                            .chain(optional)
+                            .map(|(((key, &arni), name), _)| ((key.clone(), arni), name))
+                            .unzip()
+                    } else {
+                        (fields.clone(), names)
+                    };
+                    writeln!(
+                        body,
+                        "{}class {}{}:",
+                        decorators,
+                        wrapper.wrap(r#type), // type name
+                        base_class,           // to inherit
+                    )?;
+                    if aliased && options.kind == Kind::PydanticV2BaseModel {
+                        // Allow populating fields by their names as well as aliases
+                        importing_config_dict = true;
+                        writeln!(
+                            body,
+                            "{}model_config = ConfigDict(populate_by_name=True)\n",
+                            options.indentation
+                        )?;
+                    }
+                    // lines of fields and types, with a trailing LF
+                    write!(body, "{}", wrapper.wrap((&fields, &names)))?;
+                }
+                if options.kind == Kind::PydanticV2BaseModel && is_recursive(schema, arni) {
+                    to_rebuild.push(map.to_string());
+                }
+                writeln!(body)?;
+                defined.insert(map.to_string());
+            }
+            Type::Union(
+                ref union @ Union {
+                    /* ref name_hints, */
+                    ref types,
+                    ..
+                },
+            ) if options.to_generate_type_alias_for_union && is_non_trivial(schema, types) => {
+                writeln!(
+                    body,
+                    "{} = {}",
+                    union,
+                    with_context(
+                        union,
+                        Context(schema, options, Some(&defined), &imports_from_typing)
+                    )
+                )?;
+                writeln!(body)?;
+                defined.insert(union.to_string());
+            }
+            Type::Enum(ref r#enum) if !wrapper.context.is_literal(r#enum) => {
+                importing_enum = true;
+                writeln!(body, "class {}(str, Enum):", r#enum)?;
+                for (name, value) in enum_member_names(r#enum).iter().zip(r#enum.variants.keys()) {
+                    writeln!(
+                        body,
+                        "{}{} = {}",
+                        options.indentation,
+                        name,
+                        Quoted(value, &Quote::Double)
+                    )?;
+                }
+                writeln!(body)?;
+            }
+            _ => {}
+        }
+    }
+    for name in to_rebuild {
+        writeln!(body, "{}.model_rebuild()", name)?;
+    }
+
+    let importing_email_str = imports_from_modules
+        .get("pydantic")
+        .is_some_and(|names| names.contains("EmailStr"));
+    if importing_base_class_or_class_decorators {
+        let import: Cow<str> = match options.kind {
+            Kind::Dataclass if importing_field => "from dataclasses import dataclass, field".into(),
+            Kind::Dataclass => "from dataclasses import dataclass".into(),
+            Kind::DataclassWithJSON if importing_field => {
+                "from dataclasses import dataclass, field\nfrom dataclasses_json import config, dataclass_json".into()
+            }
+            Kind::DataclassWithJSON => {
+                "from dataclasses import dataclass\nfrom dataclasses_json import dataclass_json"
+                    .into()
+            }
+            // Other names from `pydantic` (e.g. `AnyUrl`) are imported along with the base class.
+            Kind::PydanticBaseModel | Kind::PydanticV2BaseModel => {
+                let mut names = imports_from_modules.remove("pydantic").unwrap_or_default();
+                names.insert("BaseModel");
+                if importing_field || importing_config_dict {
+                    names.insert("Field");
+                }
+                if importing_config_dict {
+                    names.insert("ConfigDict");
+                }
+                format!("from pydantic import {}", names.iter().join(", ")).into()
+            }
+            Kind::PydanticDataclass => {
+                let mut names = imports_from_modules.remove("pydantic").unwrap_or_default();
+                if importing_field {
+                    names.insert("Field");
+                }
+                if names.is_empty() {
+                    "from pydantic.dataclasses import dataclass".into()
+                } else {
+                    format!(
+                        "from pydantic import {}\nfrom pydantic.dataclasses import dataclass",
+                        names.iter().join(", ")
+                    )
+                    .into()
+                }
+            }
+            Kind::TypedDict => {
+                imports_from_typing.borrow_mut().insert("TypedDict");
+                "".into()
+            }
+        };
+        writeln!(header, "from __future__ import annotations")?;
+
+        writeln!(header, "{}", import)?;
+    }
+    write_typing_imports(
+        header,
+        additional,
+        &imports_from_typing.into_inner(),
+        options.python_version,
+    )?;
+    write_module_imports(header, &imports_from_modules)?;
+    if importing_email_str {
+        writeln!(
+            additional,
+            "# 💡 `EmailStr` requires `email-validator` to be installed. pip install email-validator\n"
+        )?;
+    }
+    if importing_enum {
+        writeln!(header, "from enum import Enum")?;
+    }
+    // write!(header, "\n")?;
+    Ok(())
+}
+
+/// Python keywords that are not allowed as identifiers
+const KEYWORDS: &[&str] = &[
+    "False", "None", "True", "and", "as", "assert", "async", "await", "break", "class", "continue",
+    "def", "del", "elif", "else", "except", "finally", "for", "from", "global", "if", "import",
+    "in", "is", "lambda", "nonlocal", "not", "or", "pass", "raise", "return", "try", "while",
+    "with", "yield",
+];
+
+/// Check if a key can be used as an attribute name as is
+fn is_identifier(key: &str) -> bool {
+    let mut chars = key.chars();
+    chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_')
+        && !KEYWORDS.contains(&key)
+}
+
+/// Convert keys of a map to unique field names that are valid identifiers
+///
+/// Keys that are already valid identifiers are kept as is. Others are converted to snake_case,
+/// so that the original keys need to be recorded as aliases.
+fn field_names(map: &Map, kind: &Kind) -> Vec<String> {
+    // Fields prefixed with underscores are treated as private attributes by pydantic, and dunder
+    // ones (e.g. `__init__`) override methods of dataclasses. Fields of the names of imports or
+    // builtins shadow them in the class body (e.g. `field = None` for `field(...)`), unlike items
+    // of TypedDict that are annotations only.
+    let is_shadowing = |name: &str| *kind != Kind::TypedDict && PYTHON_NAMES.contains(&name);
+    let is_kept = |key: &str| {
+        is_identifier(key)
+            && (*kind == Kind::TypedDict || !key.starts_with('_'))
+            && !is_shadowing(key)
+            && !kind.is_reserved(key)
+    };
+    // Kept keys take precedence over converted ones in case of collision.
+    let mut seen: HashSet<String> = map
+        .fields
+        .keys()
+        .filter(|key| is_kept(key))
+        .cloned()
+        .collect();
+    map.fields
+        .keys()
+        .map(|key| {
+            if is_kept(key) {
+                return key.to_owned();
+            }
+            let name: String = key
+                .to_snake_case()
+                .chars()
+                .map(|c| if c.is_alphanumeric() { c } else { '_' })
+                .collect();
+            let name = name.trim_start_matches('_').to_owned();
+            let mut name = if name.is_empty() {
+                String::from("field")
+            } else if name.starts_with(|c: char| c.is_ascii_digit()) || kind.is_reserved(&name) {
+                format!("field_{}", name)
+            } else {
+                name
+            };
+            if KEYWORDS.contains(&name.as_str()) || is_shadowing(&name) {
+                name.push('_');
+            }
+            // Suffix with a sequence number in case of collision, e.g. `@type` and `type`
+            let mut candidate = name.clone();
+            let mut i = 1;
+            while !seen.insert(candidate.clone()) {
+                i += 1;
+                candidate = format!("{}_{}", name, i);
+            }
+            candidate
+        })
+        .collect()
+}
+
+/// Convert values of an enum to unique member names in SCREAMING_SNAKE_CASE
+fn enum_member_names(r#enum: &Enum) -> Vec<String> {
+    let mut seen = HashSet::new();
+    r#enum
+        .variants
+        .keys()
+        .map(|value| {
+            let mut name: String = value
+                .to_screaming_snake_case()
+                .chars()
+                .map(|c| if c.is_alphanumeric() { c } else { '_' })
+                .collect();
+            if name.is_empty() {
+                name.push_str("EMPTY");
+            } else if name.starts_with(|c: char| c.is_ascii_digit()) {
+                name.insert(0, '_');
+            }
+            // Suffix with a sequence number in case of collision, e.g. `a-b` and `a_b`
+            let mut candidate = name.clone();
+            let mut i = 1;
+            while !seen.insert(candidate.clone()) {
+                i += 1;
+                candidate = format!("{}_{}", name, i);
+            }
+            candidate
+        })
+        .collect()
+}
+
+impl<'i, 'c> Display for Contexted<&'i Type, Context<'c>> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let &Contexted {
+            inner: r#type,
+            context: context @ Context(schema, options, _, _),
+        } = self;
+        let version = options.python_version;
+        match r#type {
+            Type::Map(ref map) => {
+                // TODO: eliminate unnecessary heap allocation
+                write!(f, "{}", context.reference(map.to_string()))
+            }
+            Type::Enum(ref r#enum) if context.is_literal(r#enum) => write!(
+                f,
+                "{}[{}]",
+                context.import("Literal"),
+                Quoted(r#enum.variants.keys().next().unwrap(), &Quote::Double)
+            ),
+            Type::Enum(ref r#enum) => write!(f, "{}", context.reference(r#enum.to_string())),
+            Type::Union(ref union) => {
+                let is_non_trivial = is_non_trivial(schema, &union.types);
+                let not_required = union
+                    .types
+                    .contains(&schema.arena.get_index_of_primitive(Primitive::Missing))
+                    && union.types.len() > 1
+                    && options.kind == Kind::TypedDict;
+                // again, per PEP 655, use NotRequired for TypedDict item, None otherwise
+                // <del>we assume Missing/NotRequired must come with other type in a union,
+                // so we can safely use NotRequired whenever possible</del>
+                // ...ditto </del>
+                if not_required {
+                    write!(f, "{}[", context.import("NotRequired"))?;
+                }
+                if options.to_generate_type_alias_for_union && is_non_trivial {
+                    if context
+                        .members(&union.types)
+                        .contains(&schema.arena.get_index_of_primitive(Primitive::Null))
+                    {
+                        // Say, if we have `this = int | Map | None` here
+                        // we prefer
+                        // `UnionedType = Union[int, Map]; this = Union[UnionedType, None]`
+                        // instead of
+                        // `this = UnionedType = Union[int, Map, None]`
+                        //
+                        // per PEP 655:
+                        // Optional[] is too ubiquitous to deprecate, although use of it may fade
+                        // over time in favor of the T|None notation specified by PEP 604.
+                        let alias = context.reference(union.to_string());
+                        if version.has_union_operator() && !context.is_forward_reference(r#type) {
+                            write!(f, "{} | None", alias)?;
+                        } else {
+                            write!(f, "{}[{}, None]", context.import("Union"), alias)?;
+                        }
+                    } else {
+                        write!(f, "{}", context.reference(union.to_string()))?;
+                    }
+                } else {
+                    self.wrap(union).fmt(f)?;
+                }
+                if not_required {
+                    write!(f, "]")?;
+                }
+                Ok(())
+            }
+            Type::Array(r#type) => {
+                // dbg!(r#type);
+                let list = if version.has_builtin_generics() {
+                    "list"
+                } else {
+                    context.import("List")
+                };
+                write!(
+                    f,
+                    "{}[{}]",
+                    list,
+                    self.wrap(schema.arena.get(*r#type).unwrap())
+                )
+            }
+            Type::Dict(_, value) => {
+                // Keys of JSON objects are always strings, regardless of what they look like.
+                let dict = if version.has_builtin_generics() {
+                    "dict"
+                } else {
+                    context.import("Dict")
+                };
+                write!(
+                    f,
+                    "{}[str, {}]",
+                    dict,
+                    self.wrap(schema.arena.get(*value).unwrap())
+                )
+            }
+            Type::Tuple(ref types) => {
+                let tuple = if version.has_builtin_generics() {
+                    "tuple"
+                } else {
+                    context.import("Tuple")
+                };
+                write!(f, "{}[", tuple)?;
+                let mut iter = types.iter().peekable();
+                // manually intersperse
+                while let Some(&r#type) = iter.next() {
+                    self.wrap(schema.arena.get(r#type).unwrap()).fmt(f)?;
+                    if iter.peek().is_some() {
+                        write!(f, ", ")?;
+                    }
+                }
+                write!(f, "]")
+            }
+            Type::Int => write!(f, "int"),
+            Type::Float => write!(f, "float"),
+            Type::Number(ref number) if number.is_integer => write!(f, "int"),
+            Type::Number(_) => write!(f, "float"),
+            Type::Bool => write!(f, "bool"),
+            Type::String => write!(f, "str"),
+            Type::Date
+            | Type::UUID
+            | Type::PlainDate
+            | Type::Time
+            | Type::Duration
+            | Type::URL
+            | Type::Email
+            | Type::IPAddress
+            | Type::Decimal
+            | Type::Bytes => {
+                let (_, name) =
+                    python_formatted_string(r#type, options.kind.is_pydantic()).unwrap();
+                write!(f, "{}", name)
+            }
+            Type::Null => write!(f, "None"),
+            // `Missing` from the draft of PEP 655 is in neither `typing` nor `typing_extensions`.
+            Type::Missing => write!(f, "None"),
+            Type::Any => write!(f, "{}", context.import("Any")),
+        }
+    }
+}
+
+// inner of Union
+impl<'i, 'c> Display for Contexted<&'i Union, Context<'c>> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let &Contexted {
+            inner: union,
+            context: context @ Context(schema, options, _, _),
+        } = self;
+        let arnis = &*context.members(&union.types);
+        // NOTE: return value is a Union of variants instead of a concatenated string name hints;
+        let is_non_trivial = (arnis.len()
+            - arnis.contains(&schema.arena.get_index_of_primitive(Primitive::Null)) as usize
+            - arnis.contains(&schema.arena.get_index_of_primitive(Primitive::Missing)) as usize)
+            > 1;
+        let mut iter = multipeek(
+            arnis
+                .iter()
+                .cloned()
+                .map(|r#type| schema.arena.get(r#type).unwrap())
+                // again, per PEP655, use NotRequired for TypedDict item, None otherwise
+                // and specially, a single Missing is used in place of `NotRequired[]`
+                .filter(|&r#type| {
+                    options.kind != Kind::TypedDict || !r#type.is_missing() || arnis.len() == 1
+                })
+                .filter(|&r#type| {
+                    !(options.to_generate_type_alias_for_union
+                        && is_non_trivial
+                        && r#type.is_null())
+                }),
+        );
+        let _ = iter.peek(); // Discard the first
+        if iter.peek().is_some() {
+            // Regardless of possibly discarded Missing, there are at least two other inner types.
+            // None goes last, as in `X | None`
+            let (nulls, mut types): (Vec<&Type>, Vec<&Type>) =
+                iter.partition(|r#type| r#type.is_null());
+            // Quoted forward references are just strings at runtime, which do not support `|`.
+            let uses_operator = options.python_version.has_union_operator()
+                && !types
+                    .iter()
+                    .any(|&r#type| context.is_forward_reference(r#type));
+            let write_members = |f: &mut fmt::Formatter, types: Vec<&Type>| -> fmt::Result {
+                let separator = if uses_operator {
+                    " | "
+                } else {
+                    write!(f, "{}[", context.import("Union"))?;
+                    ", "
+                };
+                let mut iter = types.into_iter().peekable();
+                while let Some(r#type) = iter.next() {
+                    // manually intersperse
+                    self.wrap(r#type).fmt(f)?;
+                    if iter.peek().is_some() {
+                        write!(f, "{}", separator)?;
+                    }
+                }
+                if !uses_operator {
+                    write!(f, "]")?;
+                }
+                Ok(())
+            };
+            match context.discriminator(union) {
+                Some(discriminator) => {
+                    // The tagged members are annotated as a whole, while None stays outside.
+                    let is_nullable = !nulls.is_empty();
+                    if is_nullable && !uses_operator {
+                        write!(f, "{}[", context.import("Union"))?;
+                    }
+                    write!(f, "{}[", context.import("Annotated"))?;
+                    write_members(f, types)?;
+                    write!(
+                        f,
+                        ", Field(discriminator={})]",
+                        Quoted(&discriminator, &Quote::Double)
+                    )?;
+                    match (is_nullable, uses_operator) {
+                        (true, true) => write!(f, " | None"),
+                        (true, false) => write!(f, ", None]"),
+                        _ => Ok(()),
+                    }
+                }
+                None => {
+                    types.extend(nulls);
+                    write_members(f, types)
+                }
+            }
+        } else {
+            // Not a union anymore after dicarding Missing
+            self.wrap(
+                iter.next()
+                    .expect("The union should have at least one inner type other than Missing"),
+            )
+            .fmt(f)
+        }
+    }
+}
+
+// lines of fields of a class, with aliases for fields whose names differ from keys
+impl<'i, 'c> Display
+    for Contexted<(&'i IndexMap<String, ArenaIndex>, &'i Vec<String>), Context<'c>>
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let &Contexted {
+            inner: (fields, names),
+            context: context @ Context(schema, options, _, _),
+        } = self;
+
+        // NOTE: return value are lines of field_name: field_type instead of concatenated hints;
+        let iter = fields
+            .iter()
+            .zip(names.iter())
+            .map(|((key, &r#type), name)| (key, name, schema.arena.get(r#type).unwrap()));
+        // .peekable();
+        for (key, name, r#type) in iter {
+            // // manually intersperse
+            write!(f, "{}{}: {}", options.indentation, name, self.wrap(r#type))?;
+            let default = context.field_default(r#type);
+            if name != key {
+                let key = Quoted(key, &Quote::Double);
+                let default = default.map_or(String::new(), |default| {
+                    format!("{}, ", default.as_argument())
+                });
+                match options.kind {
+                    Kind::Dataclass => {
+                        write!(f, " = field({}metadata={{\"alias\": {}}})", default, key)?
+                    }
+                    Kind::DataclassWithJSON => write!(
+                        f,
+                        " = field({}metadata=config(field_name={}))",
+                        default, key
+                    )?,
+                    Kind::PydanticBaseModel
+                    | Kind::PydanticDataclass
+                    | Kind::PydanticV2BaseModel => write!(f, " = Field({}alias={})", default, key)?,
+                    Kind::TypedDict => unreachable!("aliased TypedDict is in functional syntax"),
+                }
+            } else {
+                match default {
+                    Some(FieldDefault::None) => write!(f, " = None")?,
+                    Some(FieldDefault::EmptyList) => write!(f, " = field(default_factory=list)")?,
+                    None => {}
+                }
+            }
+            // if iter.peek().is_none() {
+            writeln!(f)?;
+            // }
+        }
+        Ok(())
+    }
+}
+
+// items of a TypedDict in the functional syntax
+impl<'i, 'c> Display for Contexted<&'i IndexMap<String, ArenaIndex>, Context<'c>> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let &Contexted {
+            inner: fields,
+            context: Context(schema, _, _, _),
+        } = self;
+        write!(f, "{{")?;
+        let mut iter = fields.iter().peekable();
+        // manually intersperse
+        while let Some((key, &arni)) = iter.next() {
+            write!(
+                f,
+                "{}: {}",
+                Quoted(key, &Quote::Double),
+                self.wrap(schema.arena.get(arni).unwrap())
+            )?;
+            if iter.peek().is_some() {
+                write!(f, ", ")?;
+            }
+        }
+        write!(f, "}}")
+    }
+}
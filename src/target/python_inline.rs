@@ -0,0 +1,414 @@
+use indexmap::IndexSet;
+use itertools::multipeek;
+use serde::{Deserialize, Serialize};
+
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet, HashSet},
+    fmt::{self, Display, Write},
+};
+
+use crate::schema::{ArenaIndex, ITypeArena, Map, Primitive, Schema, Type, Union};
+
+use super::{
+    ordered, python_formatted_string, with_context, write_module_imports, write_typing_imports,
+    Contexted, Order, PythonVersion, Quote, Quoted, TargetGenerator, PYTHON_NAMES,
+};
+
+#[derive(Clone, Copy, Debug)] // Or just use &Context as a context
+struct Context<'c>(
+    &'c Schema,
+    &'c PythonTypedDict,
+    &'c IndexSet<ArenaIndex>,
+    &'c HashSet<ArenaIndex>,
+    &'c RefCell<BTreeSet<&'static str>>,
+);
+
+impl<'c> Context<'c> {
+    /// Check if a type is referenced by a quoted forward reference, which does not support `|`
+    fn is_forward_reference(&self, arni: ArenaIndex) -> bool {
+        let &Context(schema, options, dominant, referenceable, _) = self;
+        dominant.contains(&arni)
+            && !referenceable.contains(&arni)
+            && match *schema.arena.get(arni).unwrap() {
+                Type::Map(_) => true,
+                Type::Union(ref union) => {
+                    options.to_generate_type_alias_for_union && is_non_trivial(schema, union)
+                }
+                _ => false,
+            }
+    }
+
+    /// Use a name from `typing`, which is imported accordingly
+    fn import(&self, name: &'static str) -> &'static str {
+        self.4.borrow_mut().insert(name);
+        name
+    }
+}
+
+/// Check if a union has at least two inner types other than `Null` and `Missing`
+fn is_non_trivial(schema: &Schema, union: &Union) -> bool {
+    (union.types.len()
+        - union
+            .types
+            .contains(&schema.arena.get_index_of_primitive(Primitive::Null)) as usize
+        - union
+            .types
+            .contains(&schema.arena.get_index_of_primitive(Primitive::Missing)) as usize)
+        > 1
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PythonTypedDict {
+    pub quote_type: Quote,
+    pub to_generate_type_alias_for_union: bool,
+    pub to_nest_when_possible: bool,
+    pub order: Order,
+    pub python_version: PythonVersion,
+}
+
+// #[typetag::serde]
+impl TargetGenerator for PythonTypedDict {
+    fn reserved_names(&self) -> &'static [&'static str] {
+        PYTHON_NAMES
+    }
+
+    fn write_output(
+        &self,
+        schema: &Schema,
+        header: &mut dyn Write,
+        body: &mut dyn Write,
+        additional: &mut dyn Write,
+    ) -> fmt::Result {
+        write_output(schema, self, header, body, additional)
+    }
+}
+
+#[inline(always)]
+fn write_output(
+    schema: &Schema,
+    options: &PythonTypedDict,
+    header: &mut dyn Write,
+    body: &mut dyn Write,
+    additional: &mut dyn Write,
+) -> fmt::Result {
+    // Sorted, so that the output is deterministic
+    let imports_from_typing = RefCell::new(BTreeSet::new());
+    let mut imports_from_modules = BTreeMap::<&str, BTreeSet<&str>>::new();
+
+    let dominant = if options.to_nest_when_possible {
+        // TODO: root array type is ignored for now
+        schema.get_dominant()
+    } else {
+        schema.iter_topdown().collect()
+    };
+
+    let mut referenceable = HashSet::<ArenaIndex>::new();
+
+    // Types not yet defined are quoted as forward references when referenced.
+    for arni in ordered(schema, options.order)
+        .into_iter()
+        .filter(|arni| dominant.contains(arni))
+    {
+        let r#type = schema.arena.get(arni).unwrap();
+
+        match r#type {
+            Type::Map(map) => {
+                write!(
+                    body,
+                    "{} = {}\n\n",
+                    map,
+                    with_context(
+                        map,
+                        Context(
+                            schema,
+                            options,
+                            &dominant,
+                            &referenceable,
+                            &imports_from_typing
+                        )
+                    )
+                )?;
+                referenceable.insert(arni);
+            }
+            Type::Union(union)
+                if options.to_generate_type_alias_for_union && is_non_trivial(schema, union) =>
+            {
+                write!(
+                    body,
+                    "{} = {}\n\n",
+                    union,
+                    with_context(
+                        union,
+                        Context(
+                            schema,
+                            options,
+                            &dominant,
+                            &referenceable,
+                            &imports_from_typing
+                        )
+                    )
+                )?;
+                referenceable.insert(arni);
+            }
+            _ => (),
+        }
+    }
+    for arni in schema.iter_topdown() {
+        let r#type = schema.arena.get(arni).unwrap();
+        if r#type.is_map() {
+            imports_from_typing.borrow_mut().insert("TypedDict");
+        }
+        if let Some((Some(module), name)) = python_formatted_string(r#type, false) {
+            imports_from_modules.entry(module).or_default().insert(name);
+        }
+    }
+
+    write_typing_imports(
+        header,
+        additional,
+        &imports_from_typing.into_inner(),
+        options.python_version,
+    )?;
+    write_module_imports(header, &imports_from_modules)?;
+    // write!(header, "\n")?;
+    Ok(())
+}
+
+impl<'c> Display for Contexted<ArenaIndex, Context<'c>> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let &Contexted {
+            inner: arni,
+            context: context @ Context(schema, options, dominant, referenceable, _),
+        } = self;
+        let version = options.python_version;
+
+        let r#type = schema.arena.get(arni).unwrap();
+        // println!("{:?}\n\n", r#type);
+        match *r#type {
+            Type::Map(ref map) => {
+                if dominant.contains(&arni) {
+                    if referenceable.contains(&arni) {
+                        map.fmt(f)
+                    } else {
+                        write!(f, r#"{}{}{}"#, options.quote_type, map, options.quote_type)
+                    }
+                } else {
+                    self.wrap(map).fmt(f)
+                }
+            }
+            Type::Union(ref union) => {
+                let not_required = union
+                    .types
+                    .contains(&schema.arena.get_index_of_primitive(Primitive::Missing))
+                    && union.types.len() > 1;
+                if not_required {
+                    write!(f, "{}[", context.import("NotRequired"))?;
+                }
+                if is_non_trivial(schema, union)
+                    && options.to_generate_type_alias_for_union
+                    && dominant.contains(&arni)
+                {
+                    let nullable = union
+                        .types
+                        .contains(&schema.arena.get_index_of_primitive(Primitive::Null));
+                    let uses_operator =
+                        version.has_union_operator() && !context.is_forward_reference(arni);
+                    if nullable && !uses_operator {
+                        write!(f, "{}[", context.import("Union"))?;
+                    }
+                    if referenceable.contains(&arni) {
+                        union.fmt(f)?;
+                    } else {
+                        write!(
+                            f,
+                            r#"{}{}{}"#,
+                            options.quote_type, union, options.quote_type
+                        )?;
+                    }
+                    if nullable {
+                        // lifet up the None to the outer Union
+                        if uses_operator {
+                            write!(f, " | None")?;
+                        } else {
+                            write!(f, ", None]")?;
+                        }
+                    }
+                } else {
+                    self.wrap(union).fmt(f)?;
+                }
+                if not_required {
+                    write!(f, "]")?;
+                }
+                Ok(())
+            }
+            Type::Array(inner) => {
+                let list = if version.has_builtin_generics() {
+                    "list"
+                } else {
+                    context.import("List")
+                };
+                write!(f, "{}[{}]", list, self.wrap(inner))
+            }
+            Type::Dict(_, value) => {
+                // Keys of JSON objects are always strings, regardless of what they look like.
+                let dict = if version.has_builtin_generics() {
+                    "dict"
+                } else {
+                    context.import("Dict")
+                };
+                write!(f, "{}[str, {}]", dict, self.wrap(value))
+            }
+            Type::Tuple(ref types) => {
+                let tuple = if version.has_builtin_generics() {
+                    "tuple"
+                } else {
+                    context.import("Tuple")
+                };
+                write!(f, "{}[", tuple)?;
+                let mut iter = types.iter().peekable();
+                // manually intersperse
+                while let Some(&arni) = iter.next() {
+                    self.wrap(arni).fmt(f)?;
+                    if iter.peek().is_some() {
+                        write!(f, ", ")?;
+                    }
+                }
+                write!(f, "]")
+            }
+            Type::Enum(ref r#enum) => {
+                write!(f, "{}[", context.import("Literal"))?;
+                let mut iter = r#enum.variants.keys().peekable();
+                // manually intersperse
+                while let Some(value) = iter.next() {
+                    Quoted(value, &options.quote_type).fmt(f)?;
+                    if iter.peek().is_some() {
+                        write!(f, ", ")?;
+                    }
+                }
+                write!(f, "]")
+            }
+            Type::Int => write!(f, "int"),
+            Type::Float => write!(f, "float"),
+            Type::Number(ref number) if number.is_integer => write!(f, "int"),
+            Type::Number(_) => write!(f, "float"),
+            Type::Bool => write!(f, "bool"),
+            Type::String => write!(f, "str"),
+            Type::Date
+            | Type::UUID
+            | Type::PlainDate
+            | Type::Time
+            | Type::Duration
+            | Type::URL
+            | Type::Email
+            | Type::IPAddress
+            | Type::Decimal
+            | Type::Bytes => {
+                let (_, name) = python_formatted_string(r#type, false).unwrap();
+                write!(f, "{}", name)
+            }
+            Type::Null => write!(f, "None"),
+            // `Missing` from the draft of PEP 655 is in neither `typing` nor `typing_extensions`.
+            Type::Missing => write!(f, "None"),
+            Type::Any => write!(f, "{}", context.import("Any")),
+        }
+    }
+}
+
+impl<'i, 'c> Display for Contexted<&'i Union, Context<'c>> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let &Contexted {
+            inner: union,
+            context: context @ Context(schema, options, _dominant, _referenceable, _),
+        } = self;
+        let Union {
+            name_hints: _,
+            name: _,
+            ref types,
+        } = *union;
+        let the_null = schema.arena.get_index_of_primitive(Primitive::Null);
+        let the_missing = schema.arena.get_index_of_primitive(Primitive::Missing);
+        let is_non_trivial = (union.types.len()
+            - union.types.contains(&the_null) as usize
+            - union.types.contains(&the_missing) as usize)
+            > 1;
+
+        let mut iter = multipeek(
+            types
+                .iter()
+                .cloned()
+                .filter(|&arni| arni != the_missing || types.len() == 1)
+                .filter(|&arni| !is_non_trivial || arni != the_null),
+        );
+
+        let _ = iter.peek();
+        if iter.peek().is_some() {
+            // Regardless of a possible Missing, there are at least two other inner types.
+            // None goes last, as in `X | None`
+            let (nulls, mut types): (Vec<ArenaIndex>, Vec<ArenaIndex>) =
+                iter.partition(|&arni| arni == the_null);
+            types.extend(nulls);
+            // Quoted forward references are just strings at runtime, which do not support `|`.
+            let uses_operator = options.python_version.has_union_operator()
+                && !types.iter().any(|&arni| context.is_forward_reference(arni));
+            let separator = if uses_operator {
+                " | "
+            } else {
+                write!(f, "{}[", context.import("Union"))?;
+                ", "
+            };
+            let mut iter = types.into_iter().peekable();
+            while let Some(arni) = iter.next() {
+                // manually intersperse
+                self.wrap(arni).fmt(f)?;
+                if iter.peek().is_some() {
+                    write!(f, "{}", separator)?;
+                }
+            }
+            if !uses_operator {
+                write!(f, "]")?;
+            }
+        } else {
+            // Not a union anymore after dicarding Missing
+            self.wrap(
+                iter.next()
+                    .expect("The union should have at least one inner type other than Missing"),
+            )
+            .fmt(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl<'i, 'c> Display for Contexted<&'i Map, Context<'c>> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let &Contexted {
+            inner: map,
+            context: Context(_schema, options, _dominant, _referenceable, _),
+        } = self;
+        write!(
+            f,
+            "TypedDict({}{}{}, {{",
+            options.quote_type, map, options.quote_type
+        )?;
+        let mut iter = map.fields.iter().map(|(key, &arni)| (key, arni)).peekable();
+
+        // manually intersperse
+        while let Some((key, arni)) = iter.next() {
+            write!(
+                f,
+                "{}{}{}: {}",
+                options.quote_type,
+                key,
+                options.quote_type,
+                self.wrap(arni)
+            )?;
+            if iter.peek().is_some() {
+                write!(f, ", ")?;
+            }
+        }
+        write!(f, "}}")?;
+        write!(f, ")")?;
+        Ok(())
+    }
+}
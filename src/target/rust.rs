@@ -0,0 +1,517 @@
+use inflector::Inflector;
+use serde::{Deserialize, Serialize};
+
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    fmt::{self, Display, Write},
+};
+
+use crate::schema::{
+    dedup, ArenaIndex, Enum, ITypeArena, Map, Number, Primitive, Schema, Type, Union,
+};
+
+use super::{Contexted, Indentation, Quote, Quoted, TargetGenerator};
+
+#[derive(Clone, Copy, Debug)]
+struct Context<'c>(&'c Schema, &'c RustSerde);
+
+/// Generator for Rust `struct`s and `enum`s deriving `Serialize` and `Deserialize` from
+/// [`serde`](https://serde.rs/)
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RustSerde {
+    pub indentation: Indentation,
+    /// Make all types and fields `pub`
+    pub to_make_public: bool,
+    /// Use the narrowest integer types (e.g. `u8`) that fit all values observed, instead of `i64`
+    pub to_narrow_integers: bool,
+}
+
+impl TargetGenerator for RustSerde {
+    fn reserved_names(&self) -> &'static [&'static str] {
+        RESERVED_NAMES
+    }
+
+    fn write_output(
+        &self,
+        schema: &Schema,
+        header: &mut dyn Write,
+        body: &mut dyn Write,
+        additional: &mut dyn Write,
+    ) -> fmt::Result {
+        write_output(schema, self, header, body, additional)
+    }
+}
+
+#[inline(always)]
+fn write_output(
+    schema: &Schema,
+    options: &RustSerde,
+    header: &mut dyn Write,
+    body: &mut dyn Write,
+    _additional: &mut dyn Write,
+) -> fmt::Result {
+    let context = Context(schema, options);
+    let vis = if options.to_make_public { "pub " } else { "" };
+
+    let mut importing_hash_map = false;
+    // Sorted, so that the output is deterministic
+    let mut imports_from_chrono = BTreeSet::<&str>::new();
+    let mut importing_ip_addr = false;
+    let mut importing_uuid = false;
+
+    // Tagged unions are written as internally tagged enums, where tags are taken by serde instead
+    // of variants.
+    let mut tags = HashMap::new();
+    for (_, r#type) in schema.arena.iter() {
+        if let Some((union, key)) = r#type
+            .as_union()
+            .and_then(|union| Some((union, union.discriminator(&schema.arena)?)))
+        {
+            for &arni in union.types.iter() {
+                if let Some(map) = schema.arena.get(arni).unwrap().as_map() {
+                    tags.insert(arni, key);
+                    tags.insert(map.fields[key], key);
+                }
+            }
+        }
+    }
+
+    for arni in schema.iter_topdown() {
+        match *schema.arena.get(arni).unwrap() {
+            Type::Map(ref map) => {
+                writeln!(body, "#[derive(Debug, Clone, Serialize, Deserialize)]")?;
+                writeln!(body, "{}struct {} {{", vis, map)?;
+                let names = field_names(map);
+                for ((key, &r#type), name) in map.fields.iter().zip(names.iter()) {
+                    if tags.get(&arni) == Some(&key.as_str()) {
+                        continue;
+                    }
+                    let mut attrs = vec![];
+                    if name.trim_start_matches("r#") != key {
+                        attrs.push(format!("rename = {}", Quoted(key, &Quote::Double)));
+                    }
+                    if is_optional(schema, r#type) {
+                        attrs.push(String::from(
+                            r#"default, skip_serializing_if = "Option::is_none""#,
+                        ));
+                    }
+                    if !attrs.is_empty() {
+                        writeln!(
+                            body,
+                            "{}#[serde({})]",
+                            options.indentation,
+                            attrs.join(", ")
+                        )?;
+                    }
+                    writeln!(
+                        body,
+                        "{}{}{}: {},",
+                        options.indentation,
+                        vis,
+                        name,
+                        context.wrap_field(r#type, arni)
+                    )?;
+                }
+                write!(body, "}}\n\n")?;
+            }
+            Type::Union(ref union) if is_non_trivial(schema, union) => {
+                let discriminator = union.discriminator(&schema.arena);
+                writeln!(body, "#[derive(Debug, Clone, Serialize, Deserialize)]")?;
+                match discriminator {
+                    Some(key) => writeln!(body, "#[serde(tag = {})]", Quoted(key, &Quote::Double))?,
+                    None => writeln!(body, "#[serde(untagged)]")?,
+                }
+                writeln!(body, "{}enum {} {{", vis, union)?;
+                for (name, r#type) in union_variants(schema, union) {
+                    if let Some(key) = discriminator {
+                        let map = schema.arena.get(r#type).unwrap().as_map().unwrap();
+                        let tag = schema.arena.get(map.fields[key]).unwrap();
+                        let value = tag.as_enum().unwrap().variants.keys().next().unwrap();
+                        writeln!(
+                            body,
+                            "{}#[serde(rename = {})]",
+                            options.indentation,
+                            Quoted(value, &Quote::Double)
+                        )?;
+                    }
+                    writeln!(
+                        body,
+                        "{}{}({}),",
+                        options.indentation,
+                        name,
+                        context.wrap_field(r#type, arni)
+                    )?;
+                }
+                write!(body, "}}\n\n")?;
+            }
+            Type::Enum(ref r#enum) if !tags.contains_key(&arni) => {
+                writeln!(
+                    body,
+                    "#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]"
+                )?;
+                writeln!(body, "{}enum {} {{", vis, r#enum)?;
+                for (name, value) in enum_variant_names(r#enum)
+                    .iter()
+                    .zip(r#enum.variants.keys())
+                {
+                    if name != value {
+                        writeln!(
+                            body,
+                            "{}#[serde(rename = {})]",
+                            options.indentation,
+                            Quoted(value, &Quote::Double)
+                        )?;
+                    }
+                    writeln!(body, "{}{},", options.indentation, name)?;
+                }
+                write!(body, "}}\n\n")?;
+            }
+            Type::Dict(_, _) => importing_hash_map = true,
+            Type::Date => imports_from_chrono.extend(["DateTime", "Utc"]),
+            Type::PlainDate => {
+                imports_from_chrono.insert("NaiveDate");
+            }
+            Type::Time => {
+                imports_from_chrono.insert("NaiveTime");
+            }
+            Type::IPAddress => importing_ip_addr = true,
+            Type::UUID => importing_uuid = true,
+            _ => (),
+        }
+    }
+
+    writeln!(header, "use serde::{{Deserialize, Serialize}};")?;
+    if importing_hash_map {
+        writeln!(header, "use std::collections::HashMap;")?;
+    }
+    if importing_ip_addr {
+        writeln!(header, "use std::net::IpAddr;")?;
+    }
+    if !imports_from_chrono.is_empty() {
+        let names: Vec<&str> = imports_from_chrono.into_iter().collect();
+        writeln!(header, "use chrono::{{{}}};", names.join(", "))?;
+    }
+    if importing_uuid {
+        writeln!(header, "use uuid::Uuid;")?;
+    }
+    Ok(())
+}
+
+impl<'c> Context<'c> {
+    /// Wrap a type referenced directly (i.e. not behind a heap-allocated container like `Vec`) by
+    /// a struct or an enum
+    fn wrap_field(
+        self,
+        r#type: ArenaIndex,
+        container: ArenaIndex,
+    ) -> Contexted<(ArenaIndex, Option<ArenaIndex>), Self> {
+        Contexted {
+            inner: (r#type, Some(container)),
+            context: self,
+        }
+    }
+}
+
+/// Check if there are at least two inner types in a union other than `Null` and `Missing`
+fn is_non_trivial(schema: &Schema, union: &Union) -> bool {
+    (union.types.len()
+        - union
+            .types
+            .contains(&schema.arena.get_index_of_primitive(Primitive::Null)) as usize
+        - union
+            .types
+            .contains(&schema.arena.get_index_of_primitive(Primitive::Missing)) as usize)
+        > 1
+}
+
+/// Check if a type is written as an `Option`
+fn is_optional(schema: &Schema, r#type: ArenaIndex) -> bool {
+    match *schema.arena.get(r#type).unwrap() {
+        Type::Union(ref union) => {
+            union
+                .types
+                .contains(&schema.arena.get_index_of_primitive(Primitive::Null))
+                || union
+                    .types
+                    .contains(&schema.arena.get_index_of_primitive(Primitive::Missing))
+        }
+        Type::Null | Type::Missing => true,
+        _ => false,
+    }
+}
+
+/// Check if `to` is reachable from `from` without passing through any heap-allocated container
+///
+/// If a struct or an enum reaches itself in such a way, it is infinitely sized and hence needs
+/// boxing.
+fn reaches(schema: &Schema, from: ArenaIndex, to: ArenaIndex) -> bool {
+    let mut stack = vec![from];
+    let mut seen = HashSet::new();
+    while let Some(curr) = stack.pop() {
+        if curr == to {
+            return true;
+        }
+        if !seen.insert(curr) {
+            continue;
+        }
+        match *schema.arena.get(curr).unwrap() {
+            Type::Map(ref map) => stack.extend(map.fields.values()),
+            Type::Union(ref union) => stack.extend(union.types.iter()),
+            Type::Tuple(ref types) => stack.extend(types.iter()),
+            // Vec and HashMap are heap-allocated.
+            _ => (),
+        }
+    }
+    false
+}
+
+/// Get variants of an untagged enum of a union, in the order of being tried when deserializing
+fn union_variants(schema: &Schema, union: &Union) -> Vec<(String, ArenaIndex)> {
+    let mut types: Vec<(u8, String, ArenaIndex)> = union
+        .types
+        .iter()
+        .filter_map(|&r#type| {
+            // Untagged variants are tried in order. So more specific ones go first.
+            let (priority, name) = match *schema.arena.get(r#type).unwrap() {
+                Type::Null | Type::Missing => return None,
+                Type::Bool => (0, String::from("Bool")),
+                Type::Int => (1, String::from("Integer")),
+                Type::Float => (2, String::from("Float")),
+                Type::Number(ref number) if number.is_integer => (1, String::from("Integer")),
+                Type::Number(_) => (2, String::from("Float")),
+                Type::Date => (3, String::from("DateTime")),
+                Type::PlainDate => (3, String::from("NaiveDate")),
+                Type::Time => (3, String::from("NaiveTime")),
+                Type::UUID => (4, String::from("Uuid")),
+                Type::IPAddress => (4, String::from("IpAddr")),
+                // Strings whose formats are not checked when deserializing
+                Type::Duration | Type::URL | Type::Email | Type::Decimal | Type::Bytes => {
+                    (6, String::from("String"))
+                }
+                Type::Enum(ref r#enum) => (5, r#enum.to_string()),
+                Type::String => (6, String::from("String")),
+                Type::Tuple(_) => (7, String::from("Tuple")),
+                Type::Array(_) => (8, String::from("Array")),
+                Type::Map(ref map) => (9, map.to_string()),
+                Type::Dict(_, _) => (10, String::from("Dict")),
+                Type::Union(ref union) => (11, union.to_string()),
+                Type::Any => (12, String::from("Any")),
+            };
+            Some((priority, name, r#type))
+        })
+        .collect();
+    types.sort_by_key(|&(priority, _, _)| priority);
+    let mut seen = HashSet::new();
+    types
+        .into_iter()
+        .map(|(_, name, r#type)| (dedup(&mut seen, name), r#type))
+        .collect()
+}
+
+/// Integer types with their bounds, in the order of preference when narrowing
+///
+/// Upper bounds are exclusive, so that they are exact as `f64`. As integers in JSON never exceed
+/// `u64::MAX`, which is rounded up to `2^64` as `f64`, `u64` is not bounded above.
+const INTEGER_TYPES: &[(&str, f64, f64)] = &[
+    ("u8", 0.0, 256.0),
+    ("u16", 0.0, 65536.0),
+    ("u32", 0.0, 4294967296.0),
+    ("u64", 0.0, f64::INFINITY),
+    ("i8", -128.0, 128.0),
+    ("i16", -32768.0, 32768.0),
+    ("i32", -2147483648.0, 2147483648.0),
+    ("i64", -9223372036854775808.0, 9223372036854775808.0),
+];
+
+/// Get the Rust type of a number with its range observed
+///
+/// Integers are `i64` unless `to_narrow`, or `u64` if there are values beyond `i64`.
+fn number_type(number: &Number, to_narrow: bool) -> &'static str {
+    if !number.is_integer {
+        return "f64";
+    }
+    let fits = |&&(_, min, max): &&(&str, f64, f64)| min <= number.min && number.max < max;
+    let found = if to_narrow {
+        INTEGER_TYPES.iter().find(fits)
+    } else {
+        // `i64` is preferred to `u64`, as in `serde_json::Value`.
+        INTEGER_TYPES
+            .iter()
+            .rev()
+            .filter(|&&(name, _, _)| name == "i64" || name == "u64")
+            .find(fits)
+    };
+    found.map_or("i128", |&(name, _, _)| name)
+}
+
+/// Names of imported and prelude types that generated types must not shadow, along with `Self`
+const RESERVED_NAMES: &[&str] = &[
+    "Box",
+    "DateTime",
+    "Deserialize",
+    "HashMap",
+    "IpAddr",
+    "NaiveDate",
+    "NaiveTime",
+    "Option",
+    "Result",
+    "Self",
+    "Serialize",
+    "String",
+    "Utc",
+    "Uuid",
+    "Vec",
+];
+
+/// Rust keywords that are not allowed as identifiers, even as raw identifiers
+const NON_RAW_KEYWORDS: &[&str] = &["crate", "self", "Self", "super", "_"];
+
+/// Rust keywords that are allowed as raw identifiers
+const KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "do", "dyn",
+    "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl", "in", "let",
+    "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref", "return",
+    "static", "struct", "trait", "true", "try", "type", "typeof", "unsafe", "unsized", "use",
+    "virtual", "where", "while", "yield",
+];
+
+/// Convert keys of a map to unique field names in snake_case
+fn field_names(map: &Map) -> Vec<String> {
+    let mut seen = HashSet::new();
+    map.fields
+        .keys()
+        .map(|key| {
+            let is_snake_case = key
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+            let mut name: String = if is_snake_case {
+                key.clone()
+            } else {
+                key.to_snake_case()
+                    .chars()
+                    .map(|c| if c.is_alphanumeric() { c } else { '_' })
+                    .collect()
+            };
+            if name.is_empty() {
+                name.push_str("field");
+            } else if name.starts_with(|c: char| c.is_ascii_digit()) {
+                name.insert(0, '_');
+            }
+            if NON_RAW_KEYWORDS.contains(&name.as_str()) {
+                name.push('_');
+            }
+            let name = dedup(&mut seen, name);
+            if KEYWORDS.contains(&name.as_str()) {
+                format!("r#{}", name)
+            } else {
+                name
+            }
+        })
+        .collect()
+}
+
+/// Convert values of an enum to unique variant names in PascalCase
+fn enum_variant_names(r#enum: &Enum) -> Vec<String> {
+    let mut seen = HashSet::new();
+    r#enum
+        .variants
+        .keys()
+        .map(|value| {
+            let mut name: String = value
+                .to_pascal_case()
+                .chars()
+                .filter(|c| c.is_alphanumeric())
+                .collect();
+            if name.is_empty() {
+                name.push_str("Empty");
+            } else if name.starts_with(|c: char| c.is_ascii_digit()) {
+                name.insert(0, 'V');
+            }
+            if name == "Self" {
+                name.push('_');
+            }
+            dedup(&mut seen, name)
+        })
+        .collect()
+}
+
+// type, optionally directly referenced by a struct or an enum
+impl<'c> Display for Contexted<(ArenaIndex, Option<ArenaIndex>), Context<'c>> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let &Contexted {
+            inner: (arni, container),
+            context: Context(schema, options),
+        } = self;
+        let to_box = container.is_some_and(|container| reaches(schema, arni, container));
+        // Types in heap-allocated containers are not directly referenced anymore.
+        let indirect = |arni| Contexted {
+            inner: (arni, None),
+            context: self.context,
+        };
+
+        match *schema.arena.get(arni).unwrap() {
+            Type::Map(ref map) if to_box => write!(f, "Box<{}>", map),
+            Type::Map(ref map) => map.fmt(f),
+            Type::Union(ref union) => {
+                if is_optional(schema, arni) {
+                    write!(f, "Option<")?;
+                }
+                if is_non_trivial(schema, union) {
+                    if to_box {
+                        write!(f, "Box<{}>", union)?;
+                    } else {
+                        union.fmt(f)?;
+                    }
+                } else {
+                    let the_null = schema.arena.get_index_of_primitive(Primitive::Null);
+                    let the_missing = schema.arena.get_index_of_primitive(Primitive::Missing);
+                    match union
+                        .types
+                        .iter()
+                        .find(|&&r#type| r#type != the_null && r#type != the_missing)
+                    {
+                        Some(&r#type) => self.wrap((r#type, container)).fmt(f)?,
+                        None => write!(f, "serde_json::Value")?,
+                    }
+                }
+                if is_optional(schema, arni) {
+                    write!(f, ">")?;
+                }
+                Ok(())
+            }
+            Type::Enum(ref r#enum) => r#enum.fmt(f),
+            Type::Array(inner) => write!(f, "Vec<{}>", indirect(inner)),
+            Type::Tuple(ref types) => {
+                write!(f, "(")?;
+                let mut iter = types.iter().peekable();
+                // manually intersperse
+                while let Some(&r#type) = iter.next() {
+                    self.wrap((r#type, container)).fmt(f)?;
+                    if iter.peek().is_some() {
+                        write!(f, ", ")?;
+                    }
+                }
+                write!(f, ")")
+            }
+            // Keys of JSON objects are always strings, regardless of what they look like.
+            Type::Dict(_, value) => write!(f, "HashMap<String, {}>", indirect(value)),
+            Type::Int => write!(f, "i64"),
+            Type::Float => write!(f, "f64"),
+            Type::Number(ref number) => {
+                write!(f, "{}", number_type(number, options.to_narrow_integers))
+            }
+            Type::Bool => write!(f, "bool"),
+            Type::String => write!(f, "String"),
+            Type::Date => write!(f, "DateTime<Utc>"),
+            Type::PlainDate => write!(f, "NaiveDate"),
+            Type::Time => write!(f, "NaiveTime"),
+            Type::UUID => write!(f, "Uuid"),
+            Type::IPAddress => write!(f, "IpAddr"),
+            Type::Duration | Type::URL | Type::Email | Type::Decimal | Type::Bytes => {
+                write!(f, "String")
+            }
+            Type::Null | Type::Missing => write!(f, "Option<serde_json::Value>"),
+            Type::Any => write!(f, "serde_json::Value"),
+        }
+    }
+}
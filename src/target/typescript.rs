@@ -0,0 +1,373 @@
+use indexmap::IndexSet;
+use serde::{Deserialize, Serialize};
+
+use std::{
+    collections::BTreeSet,
+    fmt::{self, Display, Write},
+};
+
+use crate::schema::{ArenaIndex, ITypeArena, Map, Primitive, Schema, Type, Union};
+
+use super::{with_context, Contexted, Indentation, Quote, Quoted, TargetGenerator};
+
+#[derive(Clone, Copy, Debug)]
+struct Context<'c>(
+    &'c Schema,
+    &'c TypeScriptInterface,
+    &'c IndexSet<ArenaIndex>,
+);
+
+/// Generator for TypeScript `interface` and `type` declarations
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TypeScriptInterface {
+    pub indentation: Indentation,
+    pub to_generate_type_alias_for_union: bool,
+    pub to_nest_when_possible: bool,
+    /// Use branded `string` types for strings with specific formats (e.g. `Date` and `UUID`) so that
+    /// they are not interchangeable with other strings
+    pub to_brand_string_formats: bool,
+}
+
+impl TargetGenerator for TypeScriptInterface {
+    fn reserved_names(&self) -> &'static [&'static str] {
+        RESERVED_NAMES
+    }
+
+    fn write_output(
+        &self,
+        schema: &Schema,
+        header: &mut dyn Write,
+        body: &mut dyn Write,
+        additional: &mut dyn Write,
+    ) -> fmt::Result {
+        write_output(schema, self, header, body, additional)
+    }
+}
+
+#[inline(always)]
+fn write_output(
+    schema: &Schema,
+    options: &TypeScriptInterface,
+    header: &mut dyn Write,
+    body: &mut dyn Write,
+    _additional: &mut dyn Write,
+) -> fmt::Result {
+    // Sorted, so that the output is deterministic
+    let mut brands = BTreeSet::<&str>::new();
+
+    let dominant = if options.to_nest_when_possible {
+        // TODO: root array type is ignored for now
+        schema.get_dominant()
+    } else {
+        schema.iter_topdown().collect()
+    };
+    let context = Context(schema, options, &dominant);
+
+    for &arni in dominant.iter() {
+        match *schema.arena.get(arni).unwrap() {
+            Type::Map(ref map) => {
+                writeln!(body, "export interface {} {{", map)?;
+                for (key, &arni) in map.fields.iter() {
+                    writeln!(
+                        body,
+                        "{}{};",
+                        options.indentation,
+                        with_context((key.as_str(), arni), context)
+                    )?;
+                }
+                write!(body, "}}\n\n")?;
+            }
+            Type::Union(ref union)
+                if options.to_generate_type_alias_for_union && is_non_trivial(schema, union) =>
+            {
+                write!(
+                    body,
+                    "export type {} = {};\n\n",
+                    union,
+                    // the null is lifted out to where the alias is referenced
+                    with_context((union, true), context)
+                )?;
+            }
+            _ => (),
+        }
+    }
+    for arni in schema.iter_topdown() {
+        match *schema.arena.get(arni).unwrap() {
+            // A constant (e.g. the tag of a variant of a tagged union) is written inline.
+            Type::Enum(ref r#enum) if r#enum.variants.len() > 1 => {
+                write!(body, "export type {} = ", r#enum)?;
+                let mut iter = r#enum.variants.keys().peekable();
+                // manually intersperse
+                while let Some(value) = iter.next() {
+                    write!(body, "{}", Quoted(value, &Quote::Double))?;
+                    if iter.peek().is_some() {
+                        write!(body, " | ")?;
+                    }
+                }
+                write!(body, ";\n\n")?;
+            }
+            ref r#type if options.to_brand_string_formats => {
+                brands.extend(brand(r#type));
+            }
+            _ => (),
+        }
+    }
+
+    for brand in brands {
+        writeln!(
+            header,
+            r#"export type {} = string & {{ readonly __brand: "{}" }};"#,
+            brand, brand
+        )?;
+    }
+    Ok(())
+}
+
+/// Names of branded types and global types that generated types must not shadow
+const RESERVED_NAMES: &[&str] = &[
+    "Array",
+    "Boolean",
+    "Date",
+    "Error",
+    "Function",
+    "Map",
+    "Number",
+    "Object",
+    "Promise",
+    "Record",
+    "Set",
+    "String",
+    "Symbol",
+    "ISODateTime",
+    "UUID",
+    "ISODate",
+    "ISOTime",
+    "ISODuration",
+    "URLString",
+    "EmailAddress",
+    "IPAddress",
+    "DecimalString",
+    "Base64String",
+];
+
+/// Get the name of the branded `string` type for a string with a specific format (e.g. `Date`)
+fn brand(r#type: &Type) -> Option<&'static str> {
+    Some(match *r#type {
+        Type::Date => "ISODateTime",
+        Type::UUID => "UUID",
+        Type::PlainDate => "ISODate",
+        Type::Time => "ISOTime",
+        Type::Duration => "ISODuration",
+        Type::URL => "URLString",
+        Type::Email => "EmailAddress",
+        Type::IPAddress => "IPAddress",
+        Type::Decimal => "DecimalString",
+        Type::Bytes => "Base64String",
+        _ => return None,
+    })
+}
+
+/// Check if there are at least two inner types in a union other than `Null` and `Missing`
+fn is_non_trivial(schema: &Schema, union: &Union) -> bool {
+    (union.types.len()
+        - union
+            .types
+            .contains(&schema.arena.get_index_of_primitive(Primitive::Null)) as usize
+        - union
+            .types
+            .contains(&schema.arena.get_index_of_primitive(Primitive::Missing)) as usize)
+        > 1
+}
+
+/// Check if a key can be used as a property name without quoting
+fn is_identifier(key: &str) -> bool {
+    let mut chars = key.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '$')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+}
+
+impl<'c> Contexted<ArenaIndex, Context<'c>> {
+    /// Check if the type is written as a bare union of types, which needs parenthesizing in some
+    /// places (e.g. `(A | B)[]`)
+    fn is_written_as_union(&self) -> bool {
+        let &Contexted {
+            inner: arni,
+            context: Context(schema, options, dominant),
+        } = self;
+        match *schema.arena.get(arni).unwrap() {
+            Type::Union(ref union) => {
+                let nullable = union
+                    .types
+                    .contains(&schema.arena.get_index_of_primitive(Primitive::Null));
+                if options.to_generate_type_alias_for_union
+                    && is_non_trivial(schema, union)
+                    && dominant.contains(&arni)
+                {
+                    nullable
+                } else {
+                    is_non_trivial(schema, union) || nullable
+                }
+            }
+            _ => false,
+        }
+    }
+}
+
+impl<'c> Display for Contexted<ArenaIndex, Context<'c>> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let &Contexted {
+            inner: arni,
+            context: Context(schema, options, dominant),
+        } = self;
+
+        match *schema.arena.get(arni).unwrap() {
+            Type::Map(ref map) => {
+                if dominant.contains(&arni) {
+                    map.fmt(f)
+                } else {
+                    self.wrap(map).fmt(f)
+                }
+            }
+            Type::Union(ref union) => {
+                if options.to_generate_type_alias_for_union
+                    && is_non_trivial(schema, union)
+                    && dominant.contains(&arni)
+                {
+                    union.fmt(f)?;
+                    if union
+                        .types
+                        .contains(&schema.arena.get_index_of_primitive(Primitive::Null))
+                    {
+                        // Lift the null out of the type alias
+                        write!(f, " | null")?;
+                    }
+                    Ok(())
+                } else {
+                    self.wrap((union, false)).fmt(f)
+                }
+            }
+            Type::Enum(ref r#enum) if r#enum.variants.len() == 1 => {
+                let value = r#enum.variants.keys().next().unwrap();
+                Quoted(value, &Quote::Double).fmt(f)
+            }
+            Type::Enum(ref r#enum) => r#enum.fmt(f),
+            Type::Array(inner) => {
+                let inner = self.wrap(inner);
+                if inner.is_written_as_union() {
+                    write!(f, "({})[]", inner)
+                } else {
+                    write!(f, "{}[]", inner)
+                }
+            }
+            Type::Tuple(ref types) => {
+                write!(f, "[")?;
+                let mut iter = types.iter().peekable();
+                // manually intersperse
+                while let Some(&arni) = iter.next() {
+                    self.wrap(arni).fmt(f)?;
+                    if iter.peek().is_some() {
+                        write!(f, ", ")?;
+                    }
+                }
+                write!(f, "]")
+            }
+            // Keys of JSON objects are always strings, regardless of what they look like.
+            Type::Dict(_, value) => write!(f, "Record<string, {}>", self.wrap(value)),
+            Type::Int | Type::Float | Type::Number(_) => write!(f, "number"),
+            Type::Bool => write!(f, "boolean"),
+            Type::String => write!(f, "string"),
+            Type::Date
+            | Type::UUID
+            | Type::PlainDate
+            | Type::Time
+            | Type::Duration
+            | Type::URL
+            | Type::Email
+            | Type::IPAddress
+            | Type::Decimal
+            | Type::Bytes => match brand(schema.arena.get(arni).unwrap()) {
+                Some(brand) if options.to_brand_string_formats => write!(f, "{}", brand),
+                _ => write!(f, "string"),
+            },
+            Type::Null => write!(f, "null"),
+            Type::Missing => write!(f, "undefined"),
+            Type::Any => write!(f, "unknown"),
+        }
+    }
+}
+
+// inner of Union, with Missing omitted as it is expressed by an optional property, and with Null
+// omitted as well if specified
+impl<'i, 'c> Display for Contexted<(&'i Union, bool), Context<'c>> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let &Contexted {
+            inner: (union, to_omit_null),
+            context: Context(schema, _options, _dominant),
+        } = self;
+        let the_null = schema.arena.get_index_of_primitive(Primitive::Null);
+        let the_missing = schema.arena.get_index_of_primitive(Primitive::Missing);
+
+        let mut iter = union
+            .types
+            .iter()
+            .cloned()
+            .filter(|&arni| arni != the_missing)
+            .filter(|&arni| !(to_omit_null && arni == the_null))
+            .peekable();
+        if iter.peek().is_none() {
+            // Only Missing is there
+            return write!(f, "undefined");
+        }
+        // manually intersperse
+        while let Some(arni) = iter.next() {
+            self.wrap(arni).fmt(f)?;
+            if iter.peek().is_some() {
+                write!(f, " | ")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// inline object literal type
+impl<'i, 'c> Display for Contexted<&'i Map, Context<'c>> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let &Contexted { inner: map, .. } = self;
+        if map.fields.is_empty() {
+            return write!(f, "{{}}");
+        }
+        write!(f, "{{ ")?;
+        for (key, &arni) in map.fields.iter() {
+            write!(f, "{}; ", self.wrap((key.as_str(), arni)))?;
+        }
+        write!(f, "}}")
+    }
+}
+
+// property of an interface or object literal type
+impl<'i, 'c> Display for Contexted<(&'i str, ArenaIndex), Context<'c>> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let &Contexted {
+            inner: (key, arni),
+            context: Context(schema, _options, _dominant),
+        } = self;
+        if is_identifier(key) {
+            write!(f, "{}", key)?;
+        } else {
+            Quoted(key, &Quote::Double).fmt(f)?;
+        }
+        let optional = match *schema.arena.get(arni).unwrap() {
+            Type::Union(ref union) => union
+                .types
+                .contains(&schema.arena.get_index_of_primitive(Primitive::Missing)),
+            Type::Missing => true,
+            _ => false,
+        };
+        if optional {
+            write!(f, "?")?;
+        }
+        write!(f, ": {}", self.wrap(arni))
+    }
+}
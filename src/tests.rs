@@ -1,8 +1,12 @@
 use serde_json::Value;
 
 use crate::inferrer::*;
+use itertools::Itertools;
+
+use crate::schema::{ArenaIndex, ITypeArena, Primitive, Schema, SchemaIR, Type, TypeIR};
 use crate::target::{
-    Indentation, PythonClass, PythonKind, PythonTypedDict, Quote, TargetGenerator,
+    Indentation, JSONSchema, Order, PythonClass, PythonKind, PythonTypedDict, PythonVersion, Quote,
+    RustSerde, TargetGenerator, TypeScriptInterface,
 };
 
 #[test]
@@ -12,22 +16,27 @@ fn test_quicktype() {
     let v: Value = serde_json::from_str(data).unwrap();
 
     println!("{}", now.elapsed().as_millis());
-    let mut schema = infer_from_json(&v, None);
+    let mut schema = infer_from_json(&v, None).unwrap();
     println!("{}", now.elapsed().as_millis());
     dbg!(&schema);
     Optimizer {
         to_merge_similar_datatypes: true,
+        map_similarity: MapSimilarity::default(),
         to_merge_same_unions: true,
     }
-    .optimize(&mut schema);
+    .optimize(&mut schema)
+    .unwrap();
     println!("{}", now.elapsed().as_millis());
     dbg!(&schema);
     let output = PythonTypedDict {
         quote_type: Quote::Double,
         to_generate_type_alias_for_union: true,
         to_nest_when_possible: true,
+        order: Order::DependenciesFirst,
+        python_version: PythonVersion(3, 8),
     }
-    .generate(&schema);
+    .generate(&schema)
+    .unwrap();
     println!("{}", output.header);
     println!("{}", output.body);
     println!("{}", now.elapsed().as_millis());
@@ -38,18 +47,50 @@ fn test_githubstatus() {
     let data = include_str!("../tests/data/githubstatus.json");
     let v: Value = serde_json::from_str(data).unwrap();
 
-    let mut schema = infer_from_json(&v, None);
+    let mut schema = infer_from_json(&v, None).unwrap();
     Optimizer {
         to_merge_similar_datatypes: true,
+        map_similarity: MapSimilarity::default(),
         to_merge_same_unions: true,
     }
-    .optimize(&mut schema);
+    .optimize(&mut schema)
+    .unwrap();
     let _output = PythonClass {
         kind: PythonKind::Dataclass,
         to_generate_type_alias_for_union: false,
         indentation: Indentation::Space(4),
+        order: Order::Source,
+        python_version: PythonVersion(3, 8),
+    }
+    .generate(&schema)
+    .unwrap();
+}
+
+#[test]
+fn test_deterministic_output() {
+    let data = include_str!("../tests/data/githubstatus.json");
+    let v: Value = serde_json::from_str(data).unwrap();
+
+    let generate = || {
+        let mut schema = infer_from_json(&v, Some(String::from("Root"))).unwrap();
+        Optimizer::new_default().optimize(&mut schema).unwrap();
+        let output = PythonClass {
+            kind: PythonKind::Dataclass,
+            to_generate_type_alias_for_union: false,
+            indentation: Indentation::Space(4),
+            order: Order::Source,
+            python_version: PythonVersion(3, 8),
+        }
+        .generate(&schema)
+        .unwrap();
+        format!("{}{}{}", output.header, output.body, output.additional)
+    };
+    // Every HashMap gets a different random seed, so that any dependency on its iteration order
+    // would show up across runs.
+    let first = generate();
+    for _ in 0..8 {
+        assert_eq!(generate(), first);
     }
-    .generate(&schema);
 }
 
 #[test]
@@ -57,18 +98,23 @@ fn test_tree_recursion() {
     let data = include_str!("../tests/data/tree-recursion.json");
     let v: Value = serde_json::from_str(data).unwrap();
 
-    let mut schema = infer_from_json(&v, None);
+    let mut schema = infer_from_json(&v, None).unwrap();
     Optimizer {
         to_merge_similar_datatypes: true,
+        map_similarity: MapSimilarity::default(),
         to_merge_same_unions: true,
     }
-    .optimize(&mut schema);
+    .optimize(&mut schema)
+    .unwrap();
     let _output = PythonClass {
         kind: PythonKind::Dataclass,
         to_generate_type_alias_for_union: false,
         indentation: Indentation::Space(4),
+        order: Order::Source,
+        python_version: PythonVersion(3, 8),
     }
-    .generate(&schema);
+    .generate(&schema)
+    .unwrap();
 }
 
 #[test]
@@ -76,18 +122,23 @@ fn test_issue8_1() {
     let data = include_str!("../tests/data/issue8.json");
     let v: Value = serde_json::from_str(data).unwrap();
 
-    let mut schema = infer_from_json(&v, None);
+    let mut schema = infer_from_json(&v, None).unwrap();
     Optimizer {
         to_merge_similar_datatypes: true,
+        map_similarity: MapSimilarity::default(),
         to_merge_same_unions: true,
     }
-    .optimize(&mut schema);
+    .optimize(&mut schema)
+    .unwrap();
     let _output = PythonClass {
         kind: PythonKind::Dataclass,
         to_generate_type_alias_for_union: false,
         indentation: Indentation::Space(4),
+        order: Order::Source,
+        python_version: PythonVersion(3, 8),
     }
-    .generate(&schema);
+    .generate(&schema)
+    .unwrap();
 }
 
 #[test]
@@ -95,16 +146,1270 @@ fn test_issue8_2() {
     let data = include_str!("../tests/data/issue8-2.json");
     let v: Value = serde_json::from_str(data).unwrap();
 
-    let mut schema = infer_from_json(&v, None);
+    let mut schema = infer_from_json(&v, None).unwrap();
     Optimizer {
         to_merge_similar_datatypes: true,
+        map_similarity: MapSimilarity::default(),
         to_merge_same_unions: true,
     }
-    .optimize(&mut schema);
+    .optimize(&mut schema)
+    .unwrap();
     let _output = PythonClass {
         kind: PythonKind::Dataclass,
         to_generate_type_alias_for_union: false,
         indentation: Indentation::Space(4),
+        order: Order::Source,
+        python_version: PythonVersion(3, 8),
+    }
+    .generate(&schema)
+    .unwrap();
+}
+
+#[test]
+fn test_multiple_samples() {
+    let samples: Vec<Value> = serde_json::Deserializer::from_str(
+        r#"{"id": 1, "name": "foo"}
+        {"id": 2, "parent": null}"#,
+    )
+    .into_iter()
+    .collect::<Result<_, _>>()
+    .unwrap();
+
+    let schema = infer_from_json_samples(&samples, Some(String::from("Node"))).unwrap();
+    let map = schema.arena.get(schema.root).unwrap().as_map().unwrap();
+    assert_eq!(map.name_hints.iter().collect::<Vec<_>>(), vec!["Node"]);
+    assert!(schema.arena.get(map.fields["id"]).unwrap().is_int());
+    for (field, other) in [("name", Primitive::String), ("parent", Primitive::Null)] {
+        let union = schema
+            .arena
+            .get(map.fields[field])
+            .unwrap()
+            .as_union()
+            .unwrap();
+        assert_eq!(union.types.len(), 2);
+        assert!(union
+            .types
+            .contains(&schema.arena.get_index_of_primitive(Primitive::Missing)));
+        assert!(union
+            .types
+            .contains(&schema.arena.get_index_of_primitive(other)));
+    }
+}
+
+#[test]
+fn test_enum_detection() {
+    let data = r#"[
+        {"status": "active", "name": "foo"},
+        {"status": "in-progress", "name": "bar"},
+        {"status": "active", "name": "baz"},
+        {"status": "active", "name": "qux"}
+    ]"#;
+    let v: Value = serde_json::from_str(data).unwrap();
+
+    let mut schema = JSONInferrer::new_default()
+        .infer(&v, Some(String::from("Tasks")))
+        .unwrap();
+    Optimizer::new_default().optimize(&mut schema).unwrap();
+    let inner = schema.arena.get(schema.root).unwrap().as_array().unwrap();
+    let map = schema.arena.get(inner).unwrap().as_map().unwrap();
+    assert!(schema.arena.get(map.fields["name"]).unwrap().is_string());
+    let r#enum = schema
+        .arena
+        .get(map.fields["status"])
+        .unwrap()
+        .as_enum()
+        .unwrap();
+    assert_eq!(
+        r#enum.variants.iter().collect::<Vec<_>>(),
+        vec![
+            (&String::from("active"), &3),
+            (&String::from("in-progress"), &1)
+        ]
+    );
+
+    let output = PythonClass {
+        kind: PythonKind::Dataclass,
+        to_generate_type_alias_for_union: true,
+        indentation: Indentation::Space(4),
+        order: Order::Source,
+        python_version: PythonVersion(3, 8),
+    }
+    .generate(&schema)
+    .unwrap();
+    assert!(output.header.contains("from enum import Enum"));
+    assert!(output.body.contains(
+        "class Status(str, Enum):\n    ACTIVE = \"active\"\n    IN_PROGRESS = \"in-progress\"\n"
+    ));
+    assert!(output.body.contains("    status: Status\n"));
+
+    let output = PythonTypedDict {
+        quote_type: Quote::Single,
+        to_generate_type_alias_for_union: true,
+        to_nest_when_possible: true,
+        order: Order::DependenciesFirst,
+        python_version: PythonVersion(3, 8),
+    }
+    .generate(&schema)
+    .unwrap();
+    assert!(output
+        .body
+        .contains("'status': Literal['active', 'in-progress']"));
+}
+
+#[test]
+fn test_tuple_detection() {
+    let data = r#"{
+        "points": [[1.5, 2.5, "a"], [3, 4.5, "b"]],
+        "mixed": [[1, "x"], [2]],
+        "row": [1, "a", 2, "b", 3, "c", 4, "d", 5]
+    }"#;
+    let v: Value = serde_json::from_str(data).unwrap();
+
+    let mut schema = JSONInferrer {
+        to_detect_tuples: true,
+        ..Default::default()
+    }
+    .infer(&v, None)
+    .unwrap();
+    Optimizer::new_default().optimize(&mut schema).unwrap();
+    let map = schema.arena.get(schema.root).unwrap().as_map().unwrap();
+
+    let points = schema.arena.get(map.fields["points"]).unwrap();
+    let point = schema.arena.get(points.as_array().unwrap()).unwrap();
+    let types = point.as_tuple().unwrap();
+    assert_eq!(types.len(), 3);
+    assert!(schema.arena.get(types[0]).unwrap().is_float());
+    assert!(schema.arena.get(types[1]).unwrap().is_float());
+    assert!(schema.arena.get(types[2]).unwrap().is_string());
+
+    // Tuples of different lengths are treated as arrays
+    let mixed = schema.arena.get(map.fields["mixed"]).unwrap();
+    let inner = schema.arena.get(mixed.as_array().unwrap()).unwrap();
+    assert!(inner.is_array());
+
+    // Long arrays are not tuples, whether streamed or not.
+    let row = schema.arena.get(map.fields["row"]).unwrap();
+    let inner = schema.arena.get(row.as_array().unwrap()).unwrap();
+    assert_eq!(inner.as_union().unwrap().types.len(), 2);
+    let streamed = JSONInferrer {
+        to_detect_tuples: true,
+        ..Default::default()
+    }
+    .infer_reader(data.as_bytes(), None)
+    .unwrap();
+    let map = streamed.arena.get(streamed.root).unwrap().as_map().unwrap();
+    let row = streamed.arena.get(map.fields["row"]).unwrap();
+    assert!(streamed
+        .arena
+        .get(row.as_array().unwrap())
+        .unwrap()
+        .is_union());
+
+    let output = PythonClass {
+        kind: PythonKind::Dataclass,
+        to_generate_type_alias_for_union: false,
+        indentation: Indentation::Space(4),
+        order: Order::Source,
+        python_version: PythonVersion(3, 8),
+    }
+    .generate(&schema)
+    .unwrap();
+    assert!(output.header.contains("Tuple"));
+    assert!(output
+        .body
+        .contains("    points: List[Tuple[float, float, str]]\n"));
+}
+
+#[test]
+fn test_dict_detection() {
+    let data = include_str!("../tests/data/issue8.json");
+    let v: Value = serde_json::from_str(data).unwrap();
+
+    let mut schema = JSONInferrer::new_default().infer(&v, None).unwrap();
+    Optimizer::new_default().optimize(&mut schema).unwrap();
+    let map = schema.arena.get(schema.root).unwrap().as_map().unwrap();
+    for field in ["tests", "launch"] {
+        let (key, value) = schema
+            .arena
+            .get(map.fields[field])
+            .unwrap()
+            .as_dict()
+            .unwrap();
+        assert!(schema.arena.get(key).unwrap().is_string());
+        assert!(schema.arena.get(value).unwrap().is_map());
+    }
+
+    let output = PythonClass {
+        kind: PythonKind::Dataclass,
+        to_generate_type_alias_for_union: false,
+        indentation: Indentation::Space(4),
+        order: Order::Source,
+        python_version: PythonVersion(3, 8),
+    }
+    .generate(&schema)
+    .unwrap();
+    assert!(output.body.contains("    tests: Dict[str, Test]\n"));
+    assert!(output.body.contains("    launch: Dict[str, Launch]\n"));
+
+    let v: Value = serde_json::from_str(r#"{"": {"a": 1}}"#).unwrap();
+    let schema = JSONInferrer::new_default().infer(&v, None).unwrap();
+    assert!(schema.arena.get(schema.root).unwrap().is_map());
+
+    // Slots of types unioned into others (e.g. a map folded into a dict, or an enum into a string)
+    // are not left in the arena.
+    let v: Value = serde_json::from_str(
+        r#"[
+            {"by_id": {"1": {"x": 1}, "2": {"x": 2}}, "status": "active"},
+            {"by_id": {"a": {"x": 3}}, "status": "2020-12-03T08:11:21.385Z"},
+            {"by_id": {"3": {"x": 4}}, "status": "active"}
+        ]"#,
+    )
+    .unwrap();
+    let schema = JSONInferrer::new_default().infer(&v, None).unwrap();
+    let reachable: Vec<ArenaIndex> = schema.iter_topdown().collect();
+    let primitives = schema.arena.get_primitive_types();
+    assert!(schema
+        .arena
+        .iter()
+        .all(|(arni, _)| primitives.contains(&arni) || reachable.contains(&arni)));
+}
+
+#[test]
+fn test_typescript_interface() {
+    let data = r#"[
+        {"id": 1, "first-name": "foo", "parent": null, "meta": {"at": "2020-12-03T08:11:21.385Z"}},
+        {"id": 2, "first-name": "bar", "parent": 1, "extra": true, "meta": {"at": "2020-12-03T08:11:21.385Z"}}
+    ]"#;
+    let v: Value = serde_json::from_str(data).unwrap();
+
+    let mut schema = infer_from_json(&v, Some(String::from("Users"))).unwrap();
+    Optimizer::new_default().optimize(&mut schema).unwrap();
+    let output = TypeScriptInterface {
+        indentation: Indentation::Space(2),
+        to_generate_type_alias_for_union: false,
+        to_nest_when_possible: true,
+        to_brand_string_formats: true,
+    }
+    .generate(&schema)
+    .unwrap();
+    assert!(output
+        .header
+        .contains("export type ISODateTime = string & "));
+    assert!(output
+        .body
+        .contains("export interface User {\n  id: number;\n"));
+    assert!(output.body.contains("  \"first-name\": string;\n"));
+    assert!(output.body.contains("  extra?: boolean;\n"));
+    assert!(output.body.contains("  meta: { at: ISODateTime; };\n"));
+}
+
+#[test]
+fn test_python_field_aliases() {
+    let data = r#"{"User": {"first-name": "foo", "@type": "a", "type": "b", "class": 1, "2fa": true, "_id": 2, "ok": 3}}"#;
+    let v: Value = serde_json::from_str(data).unwrap();
+
+    let mut schema = infer_from_json(&v, None).unwrap();
+    Optimizer::new_default().optimize(&mut schema).unwrap();
+    let generate = |kind| {
+        PythonClass {
+            kind,
+            to_generate_type_alias_for_union: false,
+            indentation: Indentation::Space(4),
+            order: Order::Source,
+            python_version: PythonVersion(3, 8),
+        }
+        .generate(&schema)
+        .unwrap()
+    };
+
+    let output = generate(PythonKind::PydanticBaseModel);
+    assert!(output
+        .header
+        .contains("from pydantic import BaseModel, Field\n"));
+    assert!(output
+        .body
+        .contains("    first_name: str = Field(alias=\"first-name\")\n"));
+    // keys that are valid identifiers take precedence
+    assert!(output
+        .body
+        .contains("    type_2: str = Field(alias=\"@type\")\n    type: str\n"));
+    assert!(output
+        .body
+        .contains("    class_: int = Field(alias=\"class\")\n"));
+    assert!(output
+        .body
+        .contains("    field_2fa: bool = Field(alias=\"2fa\")\n"));
+    assert!(output.body.contains("    id: int = Field(alias=\"_id\")\n"));
+    assert!(output.body.contains("    ok: int\n"));
+
+    let output = generate(PythonKind::DataclassWithJSON);
+    assert!(output
+        .header
+        .contains("from dataclasses_json import config, dataclass_json\n"));
+    assert!(output
+        .body
+        .contains("    first_name: str = field(metadata=config(field_name=\"first-name\"))\n"));
+    // keys prefixed with underscores are renamed as they are for pydantic
+    assert!(output
+        .body
+        .contains("    id: int = field(metadata=config(field_name=\"_id\"))\n"));
+
+    let output = generate(PythonKind::TypedDict);
+    assert!(output
+        .body
+        .contains("User = TypedDict(\"User\", {\"first-name\": str, \"@type\": str, "));
+
+    // Fields never shadow imported names, e.g. `field` for `field(...)` of dataclasses.
+    let v: Value = serde_json::from_str(r#"[{"field": 1, "c-d": 3, "": 4}, {"x": 2}]"#).unwrap();
+    let mut schema = infer_from_json(&v, None).unwrap();
+    Optimizer::new_default().optimize(&mut schema).unwrap();
+    let output = PythonClass {
+        kind: PythonKind::Dataclass,
+        to_generate_type_alias_for_union: false,
+        indentation: Indentation::Space(4),
+        order: Order::Source,
+        python_version: PythonVersion(3, 10),
+    }
+    .generate(&schema)
+    .unwrap();
+    assert!(output.body.contains(
+        "    field_: int | None = field(default=None, metadata={\"alias\": \"field\"})\n    c_d: int | None = field(default=None, metadata={\"alias\": \"c-d\"})\n    field__2: int | None = field(default=None, metadata={\"alias\": \"\"})\n"
+    ));
+}
+
+#[test]
+fn test_order() {
+    let data = r#"{"items": [{"v": {"a": 1}}, {"v": "x"}]}"#;
+    let v: Value = serde_json::from_str(data).unwrap();
+
+    let mut schema = infer_from_json(&v, Some(String::from("Root"))).unwrap();
+    Optimizer::new_default().optimize(&mut schema).unwrap();
+    let generate = |order| {
+        PythonClass {
+            kind: PythonKind::Dataclass,
+            to_generate_type_alias_for_union: true,
+            indentation: Indentation::Space(4),
+            order,
+            python_version: PythonVersion(3, 8),
+        }
+        .generate(&schema)
+        .unwrap()
+        .body
+    };
+    let names = |body: String| -> Vec<String> {
+        body.lines()
+            .filter_map(|line| line.strip_prefix("class "))
+            .map(|line| line.trim_end_matches(':').to_owned())
+            .collect()
+    };
+
+    let output = generate(Order::DependenciesFirst);
+    assert_eq!(names(output.clone()), ["V", "Item", "Root"]);
+    // the type alias is evaluated eagerly, but no forward reference is needed
+    assert!(output.contains(" = Union[str, V]\n"));
+    let output = generate(Order::Source);
+    assert_eq!(names(output.clone()), ["Root", "Item", "V"]);
+    assert!(output.contains(" = Union[str, \"V\"]\n"));
+    // annotations are postponed, where forward references are not quoted
+    assert!(output.contains("    items: List[Item]\n"));
+    assert_eq!(names(generate(Order::RootFirst)), ["Root", "Item", "V"]);
+    assert_eq!(names(generate(Order::Alphabetical)), ["Item", "Root", "V"]);
+
+    let output = PythonTypedDict {
+        quote_type: Quote::Double,
+        to_generate_type_alias_for_union: false,
+        to_nest_when_possible: false,
+        order: Order::RootFirst,
+        python_version: PythonVersion(3, 8),
+    }
+    .generate(&schema)
+    .unwrap();
+    assert!(output
+        .body
+        .starts_with("Root = TypedDict(\"Root\", {\"items\": List[\"Item\"]})\n"));
+}
+
+#[test]
+fn test_python_version() {
+    let data = r#"[{"id": 1, "tags": ["a"], "parent": null}, {"id": 2, "tags": ["b"], "parent": 1, "extra": {"v": 1}}]"#;
+    let v: Value = serde_json::from_str(data).unwrap();
+
+    let mut schema = infer_from_json(&v, Some(String::from("Items"))).unwrap();
+    Optimizer::new_default().optimize(&mut schema).unwrap();
+    let generate_as = |kind, python_version| {
+        PythonClass {
+            kind,
+            to_generate_type_alias_for_union: true,
+            indentation: Indentation::Space(4),
+            order: Order::Source,
+            python_version,
+        }
+        .generate(&schema)
+        .unwrap()
+    };
+    let generate = |python_version| generate_as(PythonKind::TypedDict, python_version);
+
+    let output = generate(PythonVersion(3, 7));
+    assert!(output.header.contains("from typing import List, Union\n"));
+    assert!(output
+        .header
+        .contains("from typing_extensions import NotRequired, TypedDict"));
+    assert!(output.body.contains("    tags: List[str]\n"));
+    assert!(output.body.contains("    parent: Union[int, None]\n"));
+    assert!(output.additional.contains("PEP 604"));
+
+    let output = generate(PythonVersion(3, 9));
+    assert!(output
+        .header
+        .contains("from typing import TypedDict, Union\n"));
+    assert!(output
+        .header
+        .contains("from typing_extensions import NotRequired\n"));
+    assert!(output.body.contains("    tags: list[str]\n"));
+
+    let output = generate(PythonVersion(3, 11));
+    assert!(output
+        .header
+        .ends_with("from typing import NotRequired, TypedDict\n"));
+    assert!(output.body.contains("    parent: int | None\n"));
+    assert!(output.body.contains("    extra: NotRequired[Extra]\n"));
+    assert!(output.additional.is_empty());
+
+    // `Missing` is never imported, as it is in neither `typing` nor `typing_extensions`.
+    for kind in [PythonKind::PydanticBaseModel, PythonKind::PydanticDataclass] {
+        let output = generate_as(kind, PythonVersion(3, 11));
+        assert!(!output.header.contains("typing"));
+        assert!(!output.header.contains("Missing"));
+        assert!(output.body.contains("    extra: Extra | None\n"));
+    }
+
+    assert_eq!("3.10".parse(), Ok(PythonVersion(3, 10)));
+    assert!("3.6".parse::<PythonVersion>().is_err());
+}
+
+#[test]
+fn test_pydantic_v2() {
+    let data = r#"{"title": "Owner", "type": "object", "properties": {
+        "pet": {"oneOf": [
+            {"title": "Cat", "type": "object", "properties": {"kind": {"const": "cat"}, "lives": {"type": "integer"}}, "required": ["kind", "lives"]},
+            {"title": "Dog", "type": "object", "properties": {"kind": {"const": "dog"}, "bark-volume": {"type": "integer"}}, "required": ["kind"]}
+        ]},
+        "born": {"type": "string", "format": "date-time"}
+    }, "required": ["pet"]}"#;
+    let v: Value = serde_json::from_str(data).unwrap();
+
+    let mut schema = infer_from_json_schema(&v, None).unwrap();
+    Optimizer::new_default().optimize(&mut schema).unwrap();
+    let generate = |schema, python_version| {
+        PythonClass {
+            kind: PythonKind::PydanticV2BaseModel,
+            to_generate_type_alias_for_union: false,
+            indentation: Indentation::Space(4),
+            order: Order::Source,
+            python_version,
+        }
+        .generate(schema)
+        .unwrap()
+    };
+
+    let output = generate(&schema, PythonVersion(3, 10));
+    assert!(output
+        .header
+        .contains("from pydantic import BaseModel, ConfigDict, Field\n"));
+    assert!(output.header.contains("from datetime import datetime\n"));
+    assert!(output
+        .body
+        .contains("    pet: Annotated[Cat | Dog, Field(discriminator=\"kind\")]\n"));
+    assert!(output.body.contains("    born: datetime | None = None\n"));
+    assert!(output.body.contains("    kind: Literal[\"cat\"]\n"));
+    assert!(output.body.contains(
+        "class Dog(BaseModel):\n    model_config = ConfigDict(populate_by_name=True)\n\n"
+    ));
+    assert!(output
+        .body
+        .contains("    bark_volume: int | None = Field(default=None, alias=\"bark-volume\")\n"));
+    assert!(!output.body.contains("Missing"));
+    assert!(!output.body.contains("Enum"));
+
+    let data = include_str!("../tests/data/tree-recursion.json");
+    let v: Value = serde_json::from_str(data).unwrap();
+    let mut schema = infer_from_json(&v, None).unwrap();
+    Optimizer::new_default().optimize(&mut schema).unwrap();
+    let output = generate(&schema, PythonVersion(3, 8));
+    assert!(output
+        .body
+        .contains("    left: Union[LeftOrRight, None] = None\n"));
+    assert!(output.body.ends_with("\nLeftOrRight.model_rebuild()\n"));
+
+    // Names in the protected namespace of pydantic are renamed with aliases.
+    let v: Value =
+        serde_json::from_str(r#"{"model_config": 1, "model_dump": "x", "model": 2}"#).unwrap();
+    let mut schema = infer_from_json(&v, None).unwrap();
+    Optimizer::new_default().optimize(&mut schema).unwrap();
+    let output = generate(&schema, PythonVersion(3, 10));
+    assert!(output.body.contains(
+        "    model_config = ConfigDict(populate_by_name=True)\n\n    field_model_config: int = Field(alias=\"model_config\")\n    field_model_dump: str = Field(alias=\"model_dump\")\n    model: int\n"
+    ));
+}
+
+#[test]
+fn test_dataclass_defaults() {
+    let data = r#"[{"id": 1, "tags": ["a"], "note-text": "x", "score": 1}, {"id": 2, "note-text": null, "score": 2}]"#;
+    let v: Value = serde_json::from_str(data).unwrap();
+
+    let mut schema = infer_from_json(&v, Some(String::from("Item"))).unwrap();
+    Optimizer::new_default().optimize(&mut schema).unwrap();
+    let generate = |kind, python_version| {
+        PythonClass {
+            kind,
+            to_generate_type_alias_for_union: false,
+            indentation: Indentation::Space(4),
+            order: Order::Source,
+            python_version,
+        }
+        .generate(&schema)
+        .unwrap()
+    };
+
+    // Fields with defaults are moved after those without.
+    let output = generate(PythonKind::Dataclass, PythonVersion(3, 8));
+    assert!(output
+        .header
+        .contains("from dataclasses import dataclass, field\n"));
+    assert!(output.body.contains(
+        "@dataclass\nclass Item:\n    id: int\n    score: int\n    tags: List[str] = field(default_factory=list)\n    note_text: Union[str, None] = field(default=None, metadata={\"alias\": \"note-text\"})\n"
+    ));
+    assert!(!output.header.contains("Missing"));
+
+    // Or keyword-only as of Python 3.10, where fields are kept in order.
+    let output = generate(PythonKind::DataclassWithJSON, PythonVersion(3, 10));
+    assert!(output.body.contains(
+        "@dataclass_json\n@dataclass(kw_only=True)\nclass Item:\n    id: int\n    tags: list[str] = field(default_factory=list)\n"
+    ));
+    assert!(output.body.contains(
+        "    note_text: str | None = field(default=None, metadata=config(field_name=\"note-text\"))\n    score: int\n"
+    ));
+
+    // Aliases of pydantic dataclasses are defaults as well, so required fields go first.
+    let output = generate(PythonKind::PydanticDataclass, PythonVersion(3, 8));
+    assert!(output.body.contains(
+        "    score: int\n    note_text: Union[str, None] = Field(alias=\"note-text\")\n"
+    ));
+    let output = generate(PythonKind::PydanticDataclass, PythonVersion(3, 10));
+    assert!(output
+        .body
+        .starts_with("@dataclass(kw_only=True)\nclass Item:\n"));
+}
+
+#[test]
+fn test_tagged_unions() {
+    let data = r#"{"events": [
+        {"type": "click", "x": 1, "y": 2, "target": "button"},
+        {"type": "scroll", "delta": 3.5},
+        {"type": "click", "x": 3, "y": 4, "target": "link"},
+        {"type": "key", "key": "a", "modifiers": ["shift"]},
+        {"type": "scroll", "delta": 1}
+    ], "users": [{"name": "foo", "age": 1}, {"name": "bar"}]}"#;
+    let v: Value = serde_json::from_str(data).unwrap();
+
+    let mut schema = JSONInferrer::new_default()
+        .infer(&v, Some(String::from("Log")))
+        .unwrap();
+    Optimizer::new_default().optimize(&mut schema).unwrap();
+    let root = schema.arena.get(schema.root).unwrap().as_map().unwrap();
+    let inner = schema
+        .arena
+        .get(root.fields["events"])
+        .unwrap()
+        .as_array()
+        .unwrap();
+    let union = schema.arena.get(inner).unwrap().as_union().unwrap();
+    assert_eq!(union.types.len(), 3);
+    assert_eq!(union.discriminator(&schema.arena), Some("type"));
+    // Distinct names are not mistaken for tags.
+    let inner = schema
+        .arena
+        .get(root.fields["users"])
+        .unwrap()
+        .as_array()
+        .unwrap();
+    assert!(schema.arena.get(inner).unwrap().is_map());
+
+    let output = PythonClass {
+        kind: PythonKind::Dataclass,
+        to_generate_type_alias_for_union: true,
+        indentation: Indentation::Space(4),
+        order: Order::Source,
+        python_version: PythonVersion(3, 10),
+    }
+    .generate(&schema)
+    .unwrap();
+    assert!(output.body.contains(
+        "@dataclass\nclass ClickEvent:\n    type: Literal[\"click\"]\n    x: int\n    y: int\n    target: str\n"
+    ));
+    assert!(output.body.contains(
+        "@dataclass\nclass ScrollEvent:\n    type: Literal[\"scroll\"]\n    delta: float\n"
+    ));
+    // The tagged union is named after the maps.
+    assert!(output.body.contains("    events: list[Event]\n"));
+    assert!(output
+        .body
+        .contains("Event = Union[\"ClickEvent\", \"ScrollEvent\", \"KeyEvent\"]\n"));
+
+    let output = RustSerde {
+        indentation: Indentation::Space(4),
+        to_make_public: true,
+        to_narrow_integers: false,
+    }
+    .generate(&schema)
+    .unwrap();
+    assert!(output.body.contains("#[serde(tag = \"type\")]\n"));
+    assert!(output
+        .body
+        .contains("    #[serde(rename = \"click\")]\n    ClickEvent(ClickEvent),\n"));
+    assert!(!output.body.contains("pub r#type:"));
+
+    // Maps of a tag are unioned into one variant, even if another field tells them apart.
+    let data = r#"[{"type": "a", "kind": "x", "p": 1}, {"type": "a", "kind": "y", "q": 1}, {"type": "b", "kind": "x", "r": 1}]"#;
+    let v: Value = serde_json::from_str(data).unwrap();
+    let schema = JSONInferrer::new_default().infer(&v, None).unwrap();
+    let inner = schema.arena.get(schema.root).unwrap().as_array().unwrap();
+    let union = schema.arena.get(inner).unwrap().as_union().unwrap();
+    assert_eq!(union.types.len(), 2);
+    assert_eq!(union.discriminator(&schema.arena), Some("type"));
+    let variant = schema.arena.get(union.types[0]).unwrap().as_map().unwrap();
+    assert_eq!(
+        variant.fields.keys().collect_vec(),
+        ["type", "kind", "p", "q"]
+    );
+}
+
+#[test]
+fn test_map_similarity() {
+    let data = r#"{
+        "a": {"id": 1, "name": "foo", "size": 1, "owner": "x", "color": "red"},
+        "b": {"id": {"value": 2}, "name": "bar", "size": 2, "owner": "y", "color": "red"},
+        "c": {"id": 3, "name": "baz", "size": 3, "owner": null, "color": "red", "extra": true}
+    }"#;
+    let v: Value = serde_json::from_str(data).unwrap();
+    let optimized = |map_similarity| {
+        let mut schema = JSONInferrer::new_default().infer(&v, None).unwrap();
+        Optimizer {
+            to_merge_similar_datatypes: true,
+            map_similarity,
+            to_merge_same_unions: true,
+        }
+        .optimize(&mut schema)
+        .unwrap();
+        let root = schema.arena.get(schema.root).unwrap().as_map().unwrap();
+        let [a, b, c] = ["a", "b", "c"].map(|key| root.fields[key]);
+        (a == b, a == c)
+    };
+
+    // `int` and an object in a common field are not counted as the same field.
+    assert_eq!(optimized(MapSimilarity::default()), (false, true));
+    assert_eq!(
+        optimized(MapSimilarity {
+            threshold: 0.5,
+            ..MapSimilarity::default()
+        }),
+        (true, true)
+    );
+    assert_eq!(
+        optimized(MapSimilarity {
+            threshold: 0.7,
+            alpha: 3.0,
+            beta: 3.0,
+        }),
+        (false, false)
+    );
+}
+
+#[test]
+fn test_map_similarity_at_scale() {
+    // Many maps share a few keys, while only pairs of them are similar enough to be merged.
+    let maps = (0..5000).map(|i| {
+        let mut map = serde_json::json!({"id": i, "name": "x", "created_at": "y"});
+        for k in 0..6 {
+            map[format!("u{}_{}", i / 2, k)] = Value::from(k);
+        }
+        (format!("item{}", i), map)
+    });
+    let v = Value::Object(maps.collect());
+
+    let mut schema = JSONInferrer {
+        dict_detection: None,
+        ..JSONInferrer::new_default()
+    }
+    .infer(&v, None)
+    .unwrap();
+    // Checking every pair of maps would take minutes instead.
+    let now = std::time::Instant::now();
+    Optimizer::new_default().optimize(&mut schema).unwrap();
+    assert!(now.elapsed() < std::time::Duration::from_secs(30));
+    let root = schema.arena.get(schema.root).unwrap().as_map().unwrap();
+    assert_eq!(root.fields.values().unique().count(), 2500);
+    assert_eq!(root.fields["item0"], root.fields["item1"]);
+    assert_ne!(root.fields["item1"], root.fields["item2"]);
+}
+
+#[test]
+fn test_streaming() {
+    let generate = |schema: &Schema| {
+        PythonClass {
+            kind: PythonKind::Dataclass,
+            to_generate_type_alias_for_union: false,
+            indentation: Indentation::Space(4),
+            order: Order::Source,
+            python_version: PythonVersion(3, 8),
+        }
+        .generate(schema)
+        .unwrap()
+        .to_string()
+    };
+    let inferrer = JSONInferrer::new_default();
+
+    // Inferred just as from a `JSONValue`, including dicts, tuples and enums.
+    let data = include_str!("../tests/data/githubstatus.json");
+    let v: Value = serde_json::from_str(data).unwrap();
+    let expected = generate(&inferrer.infer(&v, Some(String::from("Status"))).unwrap());
+    let schema = inferrer
+        .infer_reader(data.as_bytes(), Some(String::from("Status")))
+        .unwrap();
+    assert_eq!(generate(&schema), expected);
+
+    // Long arrays are unioned chunk by chunk.
+    let data = format!(
+        "[{}]",
+        (0..3000)
+            .map(|i| format!(
+                r#"{{"id": {}, "point": [1.5, "No. {}"], "note": null}}"#,
+                i, i
+            ))
+            .join(", ")
+    );
+    let schema = inferrer
+        .infer_reader(data.as_bytes(), Some(String::from("Items")))
+        .unwrap();
+    assert!(generate(&schema).contains(
+        "@dataclass\nclass Item:\n    id: int\n    point: Tuple[float, str]\n    note: None = None\n"
+    ));
+
+    let mut streaming = inferrer.streaming(Some(String::from("Line")));
+    streaming
+        .read_many("{\"a\": 1}\n{\"a\": 2, \"b\": true}\n\n".as_bytes())
+        .unwrap();
+    let schema = streaming.finish().unwrap();
+    assert!(generate(&schema)
+        .contains("@dataclass\nclass Line:\n    a: int\n    b: Union[bool, None] = None\n"));
+
+    // Distinct strings beyond the maximum number of variants are collapsed into `String` as they
+    // are unioned, instead of all being collected until finished.
+    let mut streaming = inferrer.streaming(Some(String::from("Line")));
+    let data = (0..3000)
+        .map(|i| format!(r#"{{"id": "user-{}", "role": "admin"}}"#, i))
+        .join("\n");
+    streaming.read_many(data.as_bytes()).unwrap();
+    let schema = streaming.finish().unwrap();
+    let map = schema.arena.get(schema.root).unwrap().as_map().unwrap();
+    assert!(schema.arena.get(map.fields["id"]).unwrap().is_string());
+    assert!(schema.arena.get(map.fields["role"]).unwrap().is_enum());
+
+    // As the candidate is unioned with each string one by one, it is collapsed once beyond the
+    // maximum, while tags of a single variant are always kept.
+    let mut arena = crate::schema::TypeArena::new();
+    let candidate = |arena: &mut crate::schema::TypeArena, value: &str| {
+        arena.insert(Type::Enum(crate::schema::Enum {
+            name_hints: crate::schema::NameHints::new(),
+            name: None,
+            variants: std::iter::once((value.to_owned(), 1)).collect(),
+        }))
+    };
+    let mut unioned = candidate(&mut arena, "user-0");
+    for i in 1..=16 {
+        assert!(arena.get(unioned).unwrap().is_enum());
+        let r#type = candidate(&mut arena, &format!("user-{}", i));
+        unioned = crate::inferrer::unioner::UnionerClosure::new(&mut arena)
+            .collapsing_enums(Some(16))
+            .union([unioned, r#type])
+            .unwrap();
+    }
+    assert!(arena.get(unioned).unwrap().is_string());
+    let tag = candidate(&mut arena, "admin");
+    let tag = crate::inferrer::unioner::UnionerClosure::new(&mut arena)
+        .collapsing_enums(Some(1))
+        .union([tag])
+        .unwrap();
+    assert!(arena.get(tag).unwrap().is_enum());
+    assert!(matches!(
+        inferrer.infer_reader("[1, 2".as_bytes(), None),
+        Err(crate::Error::Parse {
+            line: 1,
+            column: 5,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn test_string_formats() {
+    let data = r#"[
+        {"day": "2024-01-31", "at": "08:30:00Z", "took": "PT1H30M", "home": "https://example.com/",
+         "mail": "someone@example.com", "ip": "192.168.0.1", "price": "12.50",
+         "when": "2024-01-31", "word": "2024-01-31", "data": "SGVsbG8sIFdvcmxkIQ==",
+         "token": "AbCdEfGh12345678IjKl", "version": "1.0"},
+        {"day": "2023-12-01", "at": "23:59", "took": "P3D", "home": "ftp://example.com/",
+         "mail": "x@y.io", "ip": "::1", "price": "-0.5",
+         "when": "2024-01-31T08:00:00Z", "word": "hello", "data": "c29tZSBiaW5hcnkgZGF0YQ+/",
+         "token": "Zz9Yy8Xx7Ww6Vv5Uu4Tt", "version": "2.10"}
+    ]"#;
+    let v: Value = serde_json::from_str(data).unwrap();
+
+    let mut schema = JSONInferrer::new_default()
+        .infer(&v, Some(String::from("Entries")))
+        .unwrap();
+    Optimizer::new_default().optimize(&mut schema).unwrap();
+    let inner = schema.arena.get(schema.root).unwrap().as_array().unwrap();
+    let map = schema.arena.get(inner).unwrap().as_map().unwrap();
+    // Dates mixed with date-times are date-times, while other mixes are just strings.
+    let expected = [
+        Primitive::PlainDate,
+        Primitive::Time,
+        Primitive::Duration,
+        Primitive::URL,
+        Primitive::Email,
+        Primitive::IPAddress,
+        Primitive::Decimal,
+        Primitive::Date,
+        Primitive::String,
+        // Alphanumeric tokens are not mistaken for Base64.
+        Primitive::Bytes,
+        Primitive::String,
+        // Versions are not mistaken for decimals.
+        Primitive::String,
+    ]
+    .map(|primitive| schema.arena.get_index_of_primitive(primitive));
+    assert_eq!(map.fields.values().copied().collect_vec(), expected);
+
+    let output = PythonClass {
+        kind: PythonKind::PydanticV2BaseModel,
+        to_generate_type_alias_for_union: true,
+        indentation: Indentation::Space(4),
+        order: Order::Source,
+        python_version: PythonVersion(3, 10),
+    }
+    .generate(&schema)
+    .unwrap();
+    assert!(output
+        .header
+        .contains("from pydantic import AnyUrl, BaseModel, EmailStr, IPvAnyAddress\n"));
+    assert!(output.header.contains(
+        "from datetime import date, datetime, time, timedelta\nfrom decimal import Decimal\n"
+    ));
+    assert!(output
+        .body
+        .contains("    day: date\n    at: time\n    took: timedelta\n    home: AnyUrl\n"));
+
+    let output = PythonClass {
+        kind: PythonKind::Dataclass,
+        to_generate_type_alias_for_union: true,
+        indentation: Indentation::Space(4),
+        order: Order::Source,
+        python_version: PythonVersion(3, 10),
+    }
+    .generate(&schema)
+    .unwrap();
+    assert!(output
+        .body
+        .contains("    home: str\n    mail: str\n    ip: str\n    price: Decimal\n"));
+
+    // Formats other than date-times and UUIDs are left undetected if not enabled.
+    let schema = JSONInferrer {
+        to_detect_string_formats: false,
+        ..JSONInferrer::new_default()
+    }
+    .infer(&v, None)
+    .unwrap();
+    let inner = schema.arena.get(schema.root).unwrap().as_array().unwrap();
+    let map = schema.arena.get(inner).unwrap().as_map().unwrap();
+    let string = schema.arena.get_index_of_primitive(Primitive::String);
+    assert_eq!(map.fields["day"], string);
+    assert_eq!(map.fields["when"], string);
+}
+
+#[test]
+fn test_number_ranges() {
+    let data = r#"[
+        {"id": 1, "delta": -5, "ratio": 1.5, "big": 18446744073709551615, "count": 1.0},
+        {"id": 200, "delta": 100, "ratio": 2, "big": 1, "count": 2.0}
+    ]"#;
+    let v: Value = serde_json::from_str(data).unwrap();
+
+    let mut schema = JSONInferrer::new_default()
+        .infer(&v, Some(String::from("Records")))
+        .unwrap();
+    Optimizer::new_default().optimize(&mut schema).unwrap();
+    let inner = schema.arena.get(schema.root).unwrap().as_array().unwrap();
+    let map = schema.arena.get(inner).unwrap().as_map().unwrap();
+    let number = |key| {
+        *schema
+            .arena
+            .get(map.fields[key])
+            .unwrap()
+            .as_number()
+            .unwrap()
+    };
+    let id = number("id");
+    assert!(id.is_integer && id.is_non_negative());
+    assert_eq!((id.min, id.max), (1.0, 200.0));
+    assert!(!number("delta").is_non_negative());
+    // Mixed with floats, integers are floats, just as `Int | Float` is `Float`.
+    let ratio = number("ratio");
+    assert!(!ratio.is_integer && !ratio.is_whole);
+    let count = number("count");
+    assert!(!count.is_integer && count.is_whole);
+    assert!(!number("big").is_safe_integer());
+
+    let generate = |to_narrow_integers| {
+        RustSerde {
+            indentation: Indentation::Space(4),
+            to_make_public: true,
+            to_narrow_integers,
+        }
+        .generate(&schema)
+        .unwrap()
+    };
+    let output = generate(false);
+    assert!(output.body.contains(
+        "    pub id: i64,\n    pub delta: i64,\n    pub ratio: f64,\n    pub big: u64,\n    pub count: f64,\n"
+    ));
+    let output = generate(true);
+    assert!(output.body.contains(
+        "    pub id: u8,\n    pub delta: i8,\n    pub ratio: f64,\n    pub big: u64,\n    pub count: f64,\n"
+    ));
+
+    // Numbers are just `Int` or `Float` if not tracked.
+    let schema = infer_from_json(&v, None).unwrap();
+    let inner = schema.arena.get(schema.root).unwrap().as_array().unwrap();
+    let map = schema.arena.get(inner).unwrap().as_map().unwrap();
+    assert_eq!(
+        map.fields["id"],
+        schema.arena.get_index_of_primitive(Primitive::Int)
+    );
+}
+
+#[test]
+fn test_rust_serde() {
+    let data = r#"[
+        {"id": 1, "type": "a", "createdAt": "2020-12-03T08:11:21.385Z", "next": {"id": 2, "type": "b", "next": null}},
+        {"id": 3, "type": "c", "next": {"id": 4, "type": "d", "createdAt": "2020-12-03T08:11:21.385Z", "next": null}}
+    ]"#;
+    let v: Value = serde_json::from_str(data).unwrap();
+
+    let mut schema = infer_from_json(&v, Some(String::from("Nodes"))).unwrap();
+    Optimizer::new_default().optimize(&mut schema).unwrap();
+    let output = RustSerde {
+        indentation: Indentation::Space(4),
+        to_make_public: true,
+        to_narrow_integers: false,
+    }
+    .generate(&schema)
+    .unwrap();
+    assert!(output.header.contains("use chrono::{DateTime, Utc};\n"));
+    assert!(output
+        .body
+        .contains("#[derive(Debug, Clone, Serialize, Deserialize)]\npub struct "));
+    assert!(output.body.contains("    pub r#type: String,\n"));
+    assert!(output.body.contains(
+        "    #[serde(rename = \"createdAt\", default, skip_serializing_if = \"Option::is_none\")]\n    pub created_at: Option<DateTime<Utc>>,\n"
+    ));
+    // recursive through Option without any heap-allocated container
+    assert!(output.body.contains("    pub next: Option<Box<"));
+}
+
+#[test]
+fn test_json_schema_input() {
+    let data = include_str!("../tests/data/json-schema.json");
+    let v: Value = serde_json::from_str(data).unwrap();
+
+    let mut schema = infer_from_json_schema(&v, None).unwrap();
+    Optimizer::new_default().optimize(&mut schema).unwrap();
+    let root = schema.arena.get(schema.root).unwrap().as_map().unwrap();
+    assert_eq!(root.to_string(), "Order");
+    let field = |name: &str| schema.arena.get(root.fields[name]).unwrap();
+    assert!(matches!(*field("id"), Type::UUID));
+    assert!(matches!(*field("createdAt"), Type::Date));
+    assert!(field("status").is_enum());
+    assert!(field("payment").as_union().unwrap().types.len() == 3);
+
+    let output = PythonClass {
+        kind: PythonKind::Dataclass,
+        to_generate_type_alias_for_union: true,
+        indentation: Indentation::Space(4),
+        order: Order::Source,
+        python_version: PythonVersion(3, 8),
+    }
+    .generate(&schema)
+    .unwrap();
+    assert!(output
+        .body
+        .contains("class LineItem:\n    sku: str\n    qty: int\n"));
+    // recursive reference via `$defs`
+    assert!(output.body.contains("List[Category]"));
+
+    // Objects without properties are dicts, and empty tuples are arrays, as neither is valid.
+    let v: Value = serde_json::from_str(
+        r#"{"type": "object", "properties": {
+            "a": {"type": "object"},
+            "b": {"type": "object", "additionalProperties": true},
+            "c": {"type": "array", "prefixItems": []}
+        }, "required": ["a", "b", "c"]}"#,
+    )
+    .unwrap();
+    let schema = infer_from_json_schema(&v, None).unwrap();
+    let root = schema.arena.get(schema.root).unwrap().as_map().unwrap();
+    let any = schema.arena.get_index_of_primitive(Primitive::Any);
+    for key in ["a", "b"] {
+        let field = schema.arena.get(root.fields[key]).unwrap();
+        assert!(matches!(*field, Type::Dict(_, value) if value == any));
+    }
+    let field = schema.arena.get(root.fields["c"]).unwrap();
+    assert_eq!(field.as_array(), Some(any));
+}
+
+#[test]
+fn test_json_schema_output() {
+    let data = include_str!("../tests/data/tree-recursion.json");
+    let v: Value = serde_json::from_str(data).unwrap();
+
+    let mut schema = infer_from_json(&v, Some(String::from("Tree"))).unwrap();
+    Optimizer::new_default().optimize(&mut schema).unwrap();
+    let output = JSONSchema {
+        indentation: Indentation::Space(2),
+    }
+    .generate(&schema)
+    .unwrap()
+    .to_string();
+    let document: Value = serde_json::from_str(&output).unwrap();
+    assert_eq!(
+        document["$schema"],
+        "https://json-schema.org/draft/2020-12/schema"
+    );
+    // The recursive type is referenced from both the root and itself.
+    let reference = document["$ref"].as_str().unwrap();
+    let def = document.pointer(&reference[1..]).unwrap();
+    assert_eq!(def["type"], "object");
+    assert_eq!(def["properties"]["value"]["type"], "integer");
+    assert!(def["required"].as_array().unwrap().len() == 3);
+
+    // It is read back as the same recursive type.
+    let mut schema = infer_from_json_schema(&document, None).unwrap();
+    Optimizer::new_default().optimize(&mut schema).unwrap();
+    let root = schema.arena.get(schema.root).unwrap().as_map().unwrap();
+    let left = schema.arena.get(root.fields["left"]).unwrap();
+    assert!(left.as_union().unwrap().types.contains(&schema.root));
+}
+
+#[test]
+fn test_schema_ir() {
+    let data = include_str!("../tests/data/quicktype.json");
+    let v: Value = serde_json::from_str(data).unwrap();
+    let generator = PythonClass {
+        kind: PythonKind::Dataclass,
+        to_generate_type_alias_for_union: true,
+        indentation: Indentation::Space(4),
+        order: Order::Source,
+        python_version: PythonVersion(3, 8),
+    };
+
+    let mut schema = infer_from_json(&v, Some(String::from("Root"))).unwrap();
+    Optimizer::new_default().optimize(&mut schema).unwrap();
+    let saved = serde_json::to_string(&schema).unwrap();
+    let loaded: Schema = serde_json::from_str(&saved).unwrap();
+    assert_eq!(
+        generator.generate(&schema).unwrap().to_string(),
+        generator.generate(&loaded).unwrap().to_string()
+    );
+    assert_eq!(loaded.to_ir(), schema.to_ir());
+
+    // Types are renamed by editing their name hints, and recursive references are kept.
+    let data = include_str!("../tests/data/tree-recursion.json");
+    let v: Value = serde_json::from_str(data).unwrap();
+    let mut schema = infer_from_json(&v, Some(String::from("Tree"))).unwrap();
+    Optimizer::new_default().optimize(&mut schema).unwrap();
+    let mut ir = schema.to_ir();
+    let key = match ir.root {
+        TypeIR::Ref(ref key) => key.to_owned(),
+        _ => panic!("the root is expected to be defined"),
+    };
+    match ir.definitions[&key] {
+        TypeIR::Map {
+            ref mut name_hints, ..
+        } => *name_hints = vec![String::from("Node")],
+        _ => panic!("the root is expected to be a map"),
+    }
+    let schema = Schema::from_ir(&ir).unwrap();
+    let root = schema.arena.get(schema.root).unwrap().as_map().unwrap();
+    assert_eq!(root.to_string(), "Node");
+    let left = schema.arena.get(root.fields["left"]).unwrap();
+    assert!(left.as_union().unwrap().types.contains(&schema.root));
+
+    ir.root = TypeIR::Ref(String::from("Leaf"));
+    assert!(matches!(
+        Schema::from_ir(&ir),
+        Err(crate::Error::InvalidIR(ref reason)) if reason.contains("`Leaf`")
+    ));
+
+    // Unions without any member other than `missing` are rejected, which no generator accepts.
+    for union in [r#"{"types": []}"#, r#"{"types": ["missing"]}"#] {
+        let data = format!(
+            r#"{{"version": 1, "root": {{"map": {{"fields": {{"a": {{"union": {}}}}}}}}}, "definitions": {{}}}}"#,
+            union
+        );
+        let ir: SchemaIR = serde_json::from_str(&data).unwrap();
+        assert!(matches!(
+            Schema::from_ir(&ir),
+            Err(crate::Error::InvalidIR(ref reason)) if reason.contains("`missing`")
+        ));
+    }
+}
+
+#[test]
+fn test_renamer() {
+    let data = include_str!("../tests/data/githubstatus.json");
+    let v: Value = serde_json::from_str(data).unwrap();
+    let mut schema = infer_from_json(&v, None).unwrap();
+    Optimizer::new_default().optimize(&mut schema).unwrap();
+    Renamer {
+        overrides: vec![
+            (String::from("$"), String::from("Summary")),
+            (String::from("$.components[*]"), String::from("Component")),
+            (String::from("$['page']"), String::from("StatusPage")),
+        ]
+        .into_iter()
+        .collect(),
+        prefix: String::from("Gh"),
+        suffix: String::new(),
+    }
+    .rename(&mut schema)
+    .unwrap();
+    let name_at = |key: &str| {
+        let root = schema.arena.get(schema.root).unwrap().as_map().unwrap();
+        let mut r#type = schema.arena.get(root.fields[key]).unwrap();
+        if let Some(inner) = r#type.as_array() {
+            r#type = schema.arena.get(inner).unwrap();
+        }
+        r#type.as_map().unwrap().to_string()
+    };
+    assert_eq!(
+        schema
+            .arena
+            .get(schema.root)
+            .unwrap()
+            .as_map()
+            .unwrap()
+            .to_string(),
+        "GhSummary"
+    );
+    assert_eq!(name_at("components"), "GhComponent");
+    assert_eq!(name_at("page"), "GhStatusPage");
+    assert_eq!(name_at("status"), "GhStatus");
+
+    for path in ["$.page.missing", "$.components[", "components"] {
+        let renamer = Renamer {
+            overrides: vec![(String::from(path), String::from("Name"))]
+                .into_iter()
+                .collect(),
+            ..Default::default()
+        };
+        assert!(matches!(
+            renamer.rename(&mut schema),
+            Err(crate::Error::InvalidNaming(_))
+        ));
+    }
+}
+
+#[test]
+fn test_unique_names() {
+    let data = r#"{
+        "user": {"id": 1, "data": {"name": "Alice", "age": 30}},
+        "order": {"total": 2.5, "currency": "USD", "data": {"items": [{"sku": "A1"}], "paid": true}},
+        "status": 1
+    }"#;
+    let v: Value = serde_json::from_str(data).unwrap();
+    let mut schema = infer_from_json(&v, None).unwrap();
+    Optimizer::new_default().optimize(&mut schema).unwrap();
+    let names = schema.unique_names(&[]);
+    assert_eq!(names[&schema.root], "Root");
+    let mut sorted = names.values().cloned().collect_vec();
+    sorted.sort();
+    assert_eq!(
+        sorted,
+        ["Item", "Order", "OrderData", "Root", "User", "UserData"]
+    );
+    assert_eq!(schema.unique_names(&[]), names);
+
+    let output = PythonClass {
+        kind: PythonKind::Dataclass,
+        to_generate_type_alias_for_union: false,
+        indentation: Indentation::Space(4),
+        order: Order::Source,
+        python_version: PythonVersion(3, 8),
+    }
+    .generate(&schema)
+    .unwrap();
+    assert!(output.body.contains("class UserData:"));
+    assert!(output.body.contains("class OrderData:"));
+    assert!(output.body.contains("data: UserData"));
+
+    // Names of imports are never taken, and unions are named after their fields.
+    let data = r#"{"list": {"a": 1}, "any": {"b": 2}, "order": {"notes": [{"v": 1}, {"v": "a"}]}}"#;
+    let v: Value = serde_json::from_str(data).unwrap();
+    let mut schema = infer_from_json(&v, None).unwrap();
+    Optimizer::new_default().optimize(&mut schema).unwrap();
+    let output = PythonClass {
+        kind: PythonKind::Dataclass,
+        to_generate_type_alias_for_union: true,
+        indentation: Indentation::Space(4),
+        order: Order::Source,
+        python_version: PythonVersion(3, 8),
+    }
+    .generate(&schema)
+    .unwrap();
+    assert!(output.body.contains("class List2:"));
+    assert!(output.body.contains("class Any2:"));
+    assert!(output.body.contains("    v: NoteV\n"));
+    assert!(output.body.contains("NoteV = Union[int, str]\n"));
+    let mut sorted = schema.unique_names(&[]).into_values().collect_vec();
+    sorted.sort();
+    assert_eq!(sorted, ["Any", "List", "Note", "NoteV", "Order", "Root"]);
+}
+
+#[test]
+fn test_errors() {
+    let error = crate::Error::from(serde_json::from_str::<Value>("{\n  \"a\": [1, }").unwrap_err());
+    assert_eq!(error.position(), Some((2, 12)));
+    assert!(error
+        .to_string()
+        .starts_with("invalid JSON at line 2 column 12: "));
+
+    let v: Value =
+        serde_json::from_str(r##"{"properties": {"a": {"$ref": "#/$defs/A"}}}"##).unwrap();
+    assert!(matches!(
+        infer_from_json_schema(&v, None),
+        Err(crate::Error::InvalidReference(ref reference)) if reference == "#/$defs/A"
+    ));
+
+    for input in ["", " \n\n  \n"] {
+        let inferrer = JSONInferrer::new_default();
+        let mut streaming = inferrer.streaming(None);
+        streaming.read_many(input.as_bytes()).unwrap();
+        let error = streaming.finish().unwrap_err();
+        assert!(matches!(error, crate::Error::NoSamples));
+        assert_eq!(error.to_string(), "no samples in input");
     }
-    .generate(&schema);
+    assert!(matches!(
+        infer_from_json_samples(&[], None),
+        Err(crate::Error::NoSamples)
+    ));
 }
@@ -1,13 +1,14 @@
 use wasm_bindgen::prelude::*;
 use console_error_panic_hook;
 
-use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
 use crate::inferrer::*;
+use crate::{Error, Result};
 use crate::target::{
-    GenOutput, Indentation, PythonClass, PythonKind, PythonTypedDict, Quote, TargetGenerator,
+    Indentation, JSONSchema, Order, PythonClass, PythonKind, PythonTypedDict, PythonVersion, Quote,
+    RustSerde, TargetGenerator, TypeScriptInterface,
 };
 
 // When the `wee_alloc` feature is enabled, use `wee_alloc` as the global
@@ -28,71 +29,145 @@ pub enum Target {
     DataclassWithJSON,
     PydanticBaseModel,
     PydanticDataclass,
+    PydanticV2BaseModel,
     TypedDictClass,
     TypedDictInline,
     NestedTypedDict,
+    TypeScriptInterface,
+    RustSerde,
+    JSONSchema,
 }
 
+/// Generate type definitions from a sample JSON
+///
+/// The root type is named after `root_name` if given, and the names of all types are decorated
+/// with `name_prefix` and `name_suffix`, which may be empty.
+///
+/// On failure, a JS `Error` is thrown, with `line` and `column` set if the error is in the input.
 #[wasm_bindgen]
-pub fn json2type(json: &str, target: Target) -> Option<String> {
+pub fn json2type(
+    json: &str,
+    target: Target,
+    root_name: Option<String>,
+    name_prefix: String,
+    name_suffix: String,
+) -> std::result::Result<String, JsValue> {
     console_error_panic_hook::set_once();
-    let v: Value = serde_json::from_str(json).ok()?;
-    let mut schema = infer_from_json(&v, None);
+    let renamer = Renamer {
+        overrides: Default::default(),
+        prefix: name_prefix,
+        suffix: name_suffix,
+    };
+    generate(json, target, root_name, &renamer).map_err(into_js_error)
+}
+
+fn into_js_error(error: Error) -> JsValue {
+    let js_error = js_sys::Error::new(&error.to_string());
+    if let Some((line, column)) = error.position() {
+        // Setting properties on a newly created object never fails.
+        let _ = js_sys::Reflect::set(&js_error, &"line".into(), &(line as u32).into());
+        let _ = js_sys::Reflect::set(&js_error, &"column".into(), &(column as u32).into());
+    }
+    js_error.into()
+}
+
+fn generate(
+    json: &str,
+    target: Target,
+    root_name: Option<String>,
+    renamer: &Renamer,
+) -> Result<String> {
+    let v: Value = serde_json::from_str(json)?;
+    let mut schema = JSONInferrer {
+        enum_detection: None,
+        to_detect_tuples: true,
+        dict_detection: Some(DictDetection::default()),
+        tagged_union_detection: None,
+        to_detect_string_formats: true,
+        to_track_number_ranges: true,
+    }
+    .infer(&v, root_name)?;
     Optimizer {
         to_merge_similar_datatypes: true,
+        map_similarity: MapSimilarity::default(),
         to_merge_same_unions: true,
     }
-    .optimize(&mut schema);
+    .optimize(&mut schema)?;
+    renamer.rename(&mut schema)?;
 
     let target: &dyn TargetGenerator = match target {
         Target::Dataclass => &PythonClass {
             kind: PythonKind::Dataclass,
             to_generate_type_alias_for_union: true,
             indentation: Indentation::Space(4),
+            order: Order::Source,
+            python_version: PythonVersion(3, 8),
         },
         Target::DataclassWithJSON => &PythonClass {
             kind: PythonKind::DataclassWithJSON,
             to_generate_type_alias_for_union: true,
             indentation: Indentation::Space(4),
+            order: Order::Source,
+            python_version: PythonVersion(3, 8),
         },
         Target::PydanticBaseModel => &PythonClass {
             kind: PythonKind::PydanticBaseModel,
             to_generate_type_alias_for_union: true,
             indentation: Indentation::Space(4),
+            order: Order::Source,
+            python_version: PythonVersion(3, 8),
         },
         Target::PydanticDataclass => &PythonClass {
             kind: PythonKind::PydanticDataclass,
             to_generate_type_alias_for_union: true,
             indentation: Indentation::Space(4),
+            order: Order::Source,
+            python_version: PythonVersion(3, 8),
+        },
+        Target::PydanticV2BaseModel => &PythonClass {
+            kind: PythonKind::PydanticV2BaseModel,
+            to_generate_type_alias_for_union: true,
+            indentation: Indentation::Space(4),
+            order: Order::Source,
+            python_version: PythonVersion(3, 8),
         },
         Target::TypedDictClass => &PythonClass {
             kind: PythonKind::TypedDict,
             to_generate_type_alias_for_union: true,
             indentation: Indentation::Space(4),
+            order: Order::Source,
+            python_version: PythonVersion(3, 8),
         },
         Target::TypedDictInline => &PythonTypedDict {
             quote_type: Quote::Double,
             to_generate_type_alias_for_union: true,
             to_nest_when_possible: false,
+            order: Order::DependenciesFirst,
+            python_version: PythonVersion(3, 8),
         },
         Target::NestedTypedDict => &PythonTypedDict {
             quote_type: Quote::Double,
             to_generate_type_alias_for_union: true,
             to_nest_when_possible: true,
+            order: Order::DependenciesFirst,
+            python_version: PythonVersion(3, 8),
+        },
+        Target::TypeScriptInterface => &TypeScriptInterface {
+            indentation: Indentation::Space(2),
+            to_generate_type_alias_for_union: true,
+            to_nest_when_possible: false,
+            to_brand_string_formats: false,
+        },
+        Target::RustSerde => &RustSerde {
+            indentation: Indentation::Space(4),
+            to_make_public: true,
+            to_narrow_integers: false,
+        },
+        Target::JSONSchema => &JSONSchema {
+            indentation: Indentation::Space(2),
         },
     };
-    let GenOutput {
-        header,
-        body,
-        additional,
-    } = target.generate(&schema);
-    Some(
-        [&header, &body, &additional]
-            .iter()
-            .cloned()
-            .filter(|s| !s.is_empty())
-            .join("\n"),
-    )
+    Ok(target.generate(&schema)?.to_string())
 }
 
 // use crate::generation::TargetLang;
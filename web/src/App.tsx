@@ -2,7 +2,7 @@ import React, { Component } from 'react';
 // import logo from './logo.svg';
 // import './App.css';
 
-import { AppBar, Box, Button, createStyles, CssBaseline, IconButton, /*FormControl, InputLabel, Select,*/ Menu, MenuItem, /* Grid, */ Theme, Toolbar, Tooltip, Typography, withStyles, WithStyles } from '@material-ui/core';
+import { AppBar, Box, Button, createStyles, CssBaseline, IconButton, /*FormControl, InputLabel, Select,*/ Menu, MenuItem, /* Grid, */ TextField, Theme, Toolbar, Tooltip, Typography, withStyles, WithStyles } from '@material-ui/core';
 import MonacoEditor from 'react-monaco-editor';
 // import SettingsIcon from '@material-ui/icons/Settings';
 import ExpandMoreIcon from '@material-ui/icons/ExpandMore';
@@ -13,8 +13,9 @@ import SwapVertIcon from '@material-ui/icons/SwapVert';
 import PACKAGE from '../package.json';
 
 
-const TARGET_OPTIONS = ['Dataclass', 'DataclassWithJSON', 'PydanticBaseModel', 'PydanticDataclass', 'TypedDictClass', 'TypedDictInline', 'NestedTypedDict'] as const
+const TARGET_OPTIONS = ['Dataclass', 'DataclassWithJSON', 'PydanticBaseModel', 'PydanticDataclass', 'PydanticV2BaseModel', 'TypedDictClass', 'TypedDictInline', 'NestedTypedDict', 'TypeScriptInterface', 'RustSerde', 'JSONSchema'] as const
 type TargetType = (typeof TARGET_OPTIONS)[number]
+const OUTPUT_LANGUAGES: Partial<Record<TargetType, string>> = { TypeScriptInterface: 'typescript', RustSerde: 'rust', JSONSchema: 'json' }
 
 const styles = (theme: Theme) => createStyles({
   root: {
@@ -43,6 +44,7 @@ const styles = (theme: Theme) => createStyles({
     marginTop: theme.spacing(2),
   },
   targetLanguage: { margin: theme.spacing(0, 0.5, 0, 1) },
+  namingField: { margin: theme.spacing(0, 1), width: '9em' },
   subtitleWrapper: {
     margin: theme.spacing(0, 2),
     [theme.breakpoints.down('sm')]: { display: 'none' },
@@ -55,9 +57,18 @@ interface Props extends WithStyles<typeof styles> { }
 interface State {
   targetMenu: any
   targetSelected: TargetType
+  naming: Naming
   output: string
 }
 
+// Names of the root type and affixes of all type names, where empty ones are left as inferred
+interface Naming {
+  rootName: string
+  prefix: string
+  suffix: string
+}
+const NAMING_FIELDS = [['rootName', 'Root name'], ['prefix', 'Name prefix'], ['suffix', 'Name suffix']] as const
+
 class App extends Component<Props, State> {
   inputEditor?: any
   outputEditor?: any
@@ -76,9 +87,13 @@ class App extends Component<Props, State> {
     }
     updateTargetInHash(target)
 
+    const naming = { rootName: '', prefix: '', suffix: '' }
+    Object.assign(naming, JSON.parse(localStorage.getItem(`${PACKAGE.name}-naming`) ?? '{}'))
+
     this.state = {
       targetMenu: null,
       targetSelected: target,
+      naming,
       output: "# No input"
     }
 
@@ -88,6 +103,7 @@ class App extends Component<Props, State> {
     this.handleInput = this.handleInput.bind(this)
     this.handleTargetIconClick = this.handleTargetIconClick.bind(this)
     this.handleTargetMenuClose = this.handleTargetMenuClose.bind(this)
+    this.handleNamingChange = this.handleNamingChange.bind(this)
   }
 
   componentDidMount() {
@@ -118,18 +134,14 @@ class App extends Component<Props, State> {
   async doGenerate() {
     const { json2type, Target } = await import('../../pkg/json2pyi')
     try {
-      // Pre-validate JSON
-      // TODO: proper error handling within Rust module
-      JSON.parse(this.input)
-    }
-    catch (_e) {
-      return
-    }
-    try {
-      const output = json2type(this.input, Target[this.state.targetSelected])
-      output && this.setState({ output })
+      const { rootName, prefix, suffix } = this.state.naming
+      const output = json2type(this.input, Target[this.state.targetSelected], rootName || undefined, prefix, suffix)
+      this.setState({ output })
     } catch (e) {
-      this.setState({ output: "# ERROR:\n" + (e as any).toString() })
+      // Errors thrown by the Rust module carry the position in the input if it is invalid JSON.
+      const { message, line, column } = e as { message: string, line?: number, column?: number }
+      const position = line === undefined ? "" : ` (line ${line}, column ${column})`
+      this.setState({ output: `# ERROR${position}:\n# ${message}` })
       console.error(e)
     }
   }
@@ -150,6 +162,12 @@ class App extends Component<Props, State> {
     this.doGenerate()
   }
 
+  handleNamingChange(event: any) {
+    const naming = { ...this.state.naming, [event.target.name]: event.target.value.trim() }
+    localStorage.setItem(`${PACKAGE.name}-naming`, JSON.stringify(naming))
+    this.setState({ naming }, () => this.doGenerate())
+  }
+
   render() {
     const classes = this.props.classes;
     const targetSelected = this.state.targetSelected //localStorage.getItem('targetSelected') ?? TARGET_OPTIONS[0]
@@ -170,6 +188,18 @@ class App extends Component<Props, State> {
             </Box>
             {/* <LinearProgress color="secondary" /> */}
             <Box sx={{ flexGrow: 1 }} />
+            {NAMING_FIELDS.map(([name, label]) => (
+              <TextField
+                key={name}
+                name={name}
+                label={label}
+                size="small"
+                variant="standard"
+                className={classes.namingField}
+                value={this.state.naming[name]}
+                onChange={this.handleNamingChange}
+              />
+            ))}
             <Tooltip title="Select Target Language" enterDelay={300}>
               <Button
                 color="inherit"
@@ -264,7 +294,7 @@ class App extends Component<Props, State> {
             <MonacoEditor
               width="100%"
               height="100%"
-              language="python"
+              language={OUTPUT_LANGUAGES[this.state.targetSelected] ?? 'python'}
               theme="vs-light"
               value={this.state.output}
               // options={{lineNumbersMinChars:3}}